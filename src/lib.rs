@@ -7,28 +7,39 @@ use lazy_static::lazy_static;
 use serde::de::DeserializeOwned;
 use serde_json::Value;
 
-use statsig::internal::StatsigDriver;
 use statsig::statsig_error::StatsigError;
 //
 // re-export public objects to top level
+pub use statsig::statsig_all_evaluations::AllEvaluations;
+pub use statsig::statsig_client::StatsigClient;
+pub use statsig::statsig_config_change::{ConfigChangeEvent, ConfigChangeListener, SpecChanges};
 pub use statsig::statsig_datastore::StatsigDatastore;
+pub use statsig::statsig_evaluation_trace::{ConditionTrace, EvaluationTrace, RuleTrace};
 pub use statsig::statsig_event::StatsigEvent;
+pub use statsig::statsig_geo_ip_resolver::GeoIpResolver;
+pub use statsig::statsig_initialize_details::InitializeDetails;
+pub use statsig::statsig_network_provider::{
+    NetworkEndpoint, NetworkMethod, NetworkProvider, NetworkRequest, NetworkResponse,
+    ReqwestNetworkProvider,
+};
 pub use statsig::statsig_options::StatsigOptions;
 pub use statsig::statsig_user::StatsigUser;
+pub use statsig::statsig_user_agent_resolver::UserAgentResolver;
+pub use statsig::statsig_user_context::StatsigUserContext;
+pub use statsig::statsig_user_persistent_storage::{
+    StickyValues, UserPersistedValues, UserPersistentStorage,
+};
 pub use statsig::internal::{DynamicConfig, FeatureGate, Layer};
 pub use crate::statsig::internal::{EvalDetails, EvaluationReason};
 use futures::future::Shared;
 use futures::FutureExt;
 use tokio::sync::futures::Notified;
 use tokio::sync::Notify;
-use tokio::task::spawn_blocking;
-
-use crate::statsig::internal::LayerLogData;
 
 mod statsig;
 
 lazy_static! {
-    static ref DRIVER: Arc<RwLock<Option<StatsigDriver>>> = Arc::from(RwLock::from(None));
+    static ref CLIENT: Arc<RwLock<Option<StatsigClient>>> = Arc::from(RwLock::from(None));
     static ref STATSIG_INIT_NOTIFY: Arc<Notify> = Arc::new(Notify::new());
     static ref STATSIG_INIT_NOTIFIED_FUTURE: Shared<Notified<'static>> =
         STATSIG_INIT_NOTIFY.notified().shared();
@@ -45,38 +56,52 @@ impl Statsig {
         secret: &str,
         options: StatsigOptions,
     ) -> Option<StatsigError> {
-        match DRIVER.read().ok() {
+        Self::initialize_impl(secret, options).await.err()
+    }
+
+    // Like initialize_with_options, but also reports whether config specs
+    // were loaded and where they came from.
+    pub async fn initialize_with_details(
+        secret: &str,
+        options: StatsigOptions,
+    ) -> InitializeDetails {
+        match Self::initialize_impl(secret, options).await {
+            Ok(details) => details,
+            Err(e) => InitializeDetails::from_error(e),
+        }
+    }
+
+    async fn initialize_impl(
+        secret: &str,
+        options: StatsigOptions,
+    ) -> Result<InitializeDetails, StatsigError> {
+        match CLIENT.read().ok() {
             Some(read_guard) => {
                 if read_guard.is_some() {
-                    return Some(StatsigError::AlreadyInitialized);
+                    return Err(StatsigError::AlreadyInitialized);
                 }
             }
             None => {
-                return Some(StatsigError::SingletonLockFailure);
+                return Err(StatsigError::SingletonLockFailure);
             }
         }
 
-        let driver = unwrap_or_return!(
-            StatsigDriver::new(secret, options).ok(),
-            Some(StatsigError::InstantiationFailure)
-        );
+        let client = StatsigClient::new(secret, options)?;
 
-        driver.initialize().await;
+        let details = client.initialize().await;
 
-        let mut write_guard = unwrap_or_return!(
-            DRIVER.write().ok(),
-            Some(StatsigError::SingletonLockFailure)
-        );
+        let mut write_guard =
+            unwrap_or_return!(CLIENT.write().ok(), Err(StatsigError::SingletonLockFailure));
 
-        *write_guard = Some(driver);
+        *write_guard = Some(client);
 
         STATSIG_INIT_NOTIFY.notify_waiters();
 
-        None
+        Ok(details)
     }
 
     pub fn is_initialized() -> bool {
-        DRIVER.read().map_or(false, |guard| guard.is_some())
+        CLIENT.read().is_ok_and(|guard| guard.is_some())
     }
 
     pub fn wait_for_initialization() -> Shared<Notified<'static>> {
@@ -84,38 +109,30 @@ impl Statsig {
     }
 
     pub async fn shutdown() -> Option<StatsigError> {
-        let driver_clone = Arc::clone(&DRIVER);
-        match spawn_blocking(move || {
-            let mut write_guard = unwrap_or_return!(
-                driver_clone.write().ok(),
-                Err(StatsigError::SingletonLockFailure)
-            );
-
-            if let Some(driver) = write_guard.take() {
-                driver.shutdown();
-            }
-            Ok(())
-        })
-        .await
-        {
-            Ok(_t) => None,
-            Err(_e) => Some(StatsigError::ShutdownFailure),
+        let client = match CLIENT.write().ok() {
+            Some(mut write_guard) => write_guard.take(),
+            None => return Some(StatsigError::SingletonLockFailure),
+        };
+
+        match client {
+            Some(client) => client.shutdown().await,
+            None => None,
         }
     }
 
     pub fn check_gate(user: &StatsigUser, gate_name: &str) -> Result<bool, StatsigError> {
-        Self::use_driver(|driver| Ok(driver.check_gate(user, gate_name)))
+        Self::use_client(|client| Ok(client.check_gate(user, gate_name)))
     }
 
     pub fn get_feature_gate(user: &StatsigUser, gate_name: &str) -> Result<FeatureGate, StatsigError> {
-        Self::use_driver(|driver| Ok(driver.get_feature_gate(user, gate_name)))
+        Self::use_client(|client| Ok(client.get_feature_gate(user, gate_name)))
     }
 
     pub fn get_config<T: DeserializeOwned>(
         user: &StatsigUser,
         config_name: &str,
     ) -> Result<DynamicConfig<T>, StatsigError> {
-        Self::use_driver(|driver| Ok(driver.get_config(user, config_name)))
+        Self::use_client(|client| Ok(client.get_config(user, config_name)))
     }
 
     pub fn get_experiment<T: DeserializeOwned>(
@@ -125,43 +142,225 @@ impl Statsig {
         Self::get_config(user, experiment_name)
     }
 
+    pub fn get_experiment_with_persisted_assignment<T: DeserializeOwned>(
+        user: &StatsigUser,
+        experiment_name: &str,
+    ) -> Result<DynamicConfig<T>, StatsigError> {
+        Self::use_client(|client| {
+            Ok(client.get_experiment_with_persisted_assignment(user, experiment_name))
+        })
+    }
+
     pub fn get_layer(user: &StatsigUser, layer_name: &str) -> Result<Layer, StatsigError> {
-        Self::use_driver(|driver| Ok(driver.get_layer(user, layer_name)))
+        Self::use_client(|client| Ok(client.get_layer(user, layer_name)))
+    }
+
+    pub fn check_gate_with_exposure_logging_disabled(
+        user: &StatsigUser,
+        gate_name: &str,
+    ) -> Result<bool, StatsigError> {
+        Self::use_client(|client| {
+            Ok(client.check_gate_with_exposure_logging_disabled(user, gate_name))
+        })
+    }
+
+    pub fn get_feature_gate_with_exposure_logging_disabled(
+        user: &StatsigUser,
+        gate_name: &str,
+    ) -> Result<FeatureGate, StatsigError> {
+        Self::use_client(|client| {
+            Ok(client.get_feature_gate_with_exposure_logging_disabled(user, gate_name))
+        })
+    }
+
+    pub fn get_config_with_exposure_logging_disabled<T: DeserializeOwned>(
+        user: &StatsigUser,
+        config_name: &str,
+    ) -> Result<DynamicConfig<T>, StatsigError> {
+        Self::use_client(|client| {
+            Ok(client.get_config_with_exposure_logging_disabled(user, config_name))
+        })
+    }
+
+    pub fn get_experiment_with_exposure_logging_disabled<T: DeserializeOwned>(
+        user: &StatsigUser,
+        experiment_name: &str,
+    ) -> Result<DynamicConfig<T>, StatsigError> {
+        Self::get_config_with_exposure_logging_disabled(user, experiment_name)
+    }
+
+    pub fn get_layer_with_exposure_logging_disabled(
+        user: &StatsigUser,
+        layer_name: &str,
+    ) -> Result<Layer, StatsigError> {
+        Self::use_client(|client| {
+            Ok(client.get_layer_with_exposure_logging_disabled(user, layer_name))
+        })
+    }
+
+    pub fn manually_log_gate_exposure(user: &StatsigUser, gate_name: &str) -> Option<StatsigError> {
+        Self::use_client(|client| {
+            client.manually_log_gate_exposure(user, gate_name);
+            Ok(())
+        })
+        .err()
+    }
+
+    pub fn manually_log_config_exposure(
+        user: &StatsigUser,
+        config_name: &str,
+    ) -> Option<StatsigError> {
+        Self::use_client(|client| {
+            client.manually_log_config_exposure(user, config_name);
+            Ok(())
+        })
+        .err()
+    }
+
+    pub fn manually_log_experiment_exposure(
+        user: &StatsigUser,
+        experiment_name: &str,
+    ) -> Option<StatsigError> {
+        Self::manually_log_config_exposure(user, experiment_name)
+    }
+
+    pub fn manually_log_layer_parameter_exposure(
+        user: &StatsigUser,
+        layer_name: &str,
+        parameter_name: &str,
+    ) -> Option<StatsigError> {
+        Self::use_client(|client| {
+            client.manually_log_layer_parameter_exposure(user, layer_name, parameter_name);
+            Ok(())
+        })
+        .err()
     }
 
     pub fn log_event(user: &StatsigUser, event: StatsigEvent) -> Option<StatsigError> {
-        let res = Self::use_driver(move |driver| {
-            driver.log_event(user, event);
+        let res = Self::use_client(move |client| {
+            client.log_event(user, event);
             Ok(())
         });
 
-        match res {
-            Err(e) => Some(e),
-            _ => None,
-        }
+        res.err()
+    }
+
+    pub fn add_config_change_listener(
+        listener: impl Fn(&ConfigChangeEvent) + Send + Sync + 'static,
+    ) -> Option<StatsigError> {
+        Self::use_client(|client| {
+            client.add_config_change_listener(listener);
+            Ok(())
+        })
+        .err()
+    }
+
+    pub fn get_dropped_event_count() -> Result<u64, StatsigError> {
+        Self::use_client(|client| Ok(client.get_dropped_event_count()))
     }
 
     pub fn get_client_initialize_response(user: &StatsigUser) -> Result<Value, StatsigError> {
-        Self::use_driver(|driver| Ok(driver.get_client_initialize_response(user)))
+        Self::use_client(|client| Ok(client.get_client_initialize_response(user)))
     }
 
-    pub(crate) fn log_layer_parameter_exposure(
-        layer: &Layer,
-        parameter_name: &str,
-        log_data: &LayerLogData,
-    ) {
-        let _ = Self::use_driver(|driver| {
-            driver.log_layer_parameter_exposure(layer, parameter_name, log_data);
+    pub fn with_user_context(user: &StatsigUser) -> Result<StatsigUserContext, StatsigError> {
+        Self::use_client(|client| Ok(client.with_user_context(user)))
+    }
+
+    pub fn evaluate_all(user: &StatsigUser) -> Result<AllEvaluations, StatsigError> {
+        Self::use_client(|client| Ok(client.evaluate_all(user)))
+    }
+
+    pub fn evaluate_all_with_exposure_logging_disabled(
+        user: &StatsigUser,
+    ) -> Result<AllEvaluations, StatsigError> {
+        Self::use_client(|client| Ok(client.evaluate_all_with_exposure_logging_disabled(user)))
+    }
+
+    pub fn explain_gate(
+        user: &StatsigUser,
+        gate_name: &str,
+    ) -> Result<EvaluationTrace, StatsigError> {
+        Self::use_client(|client| Ok(client.explain_gate(user, gate_name)))
+    }
+
+    pub fn explain_config(
+        user: &StatsigUser,
+        config_name: &str,
+    ) -> Result<EvaluationTrace, StatsigError> {
+        Self::use_client(|client| Ok(client.explain_config(user, config_name)))
+    }
+
+    pub fn override_gate(gate_name: &str, value: bool, id: Option<&str>) -> Option<StatsigError> {
+        Self::use_client(|client| {
+            client.override_gate(gate_name, value, id);
             Ok(())
-        });
+        })
+        .err()
+    }
+
+    pub fn override_config(
+        config_name: &str,
+        value: Value,
+        id: Option<&str>,
+    ) -> Option<StatsigError> {
+        Self::use_client(|client| {
+            client.override_config(config_name, value, id);
+            Ok(())
+        })
+        .err()
     }
 
-    fn use_driver<T>(
-        func: impl FnOnce(&StatsigDriver) -> Result<T, StatsigError>,
+    pub fn override_layer(
+        layer_name: &str,
+        value: Value,
+        id: Option<&str>,
+    ) -> Option<StatsigError> {
+        Self::use_client(|client| {
+            client.override_layer(layer_name, value, id);
+            Ok(())
+        })
+        .err()
+    }
+
+    pub fn remove_gate_override(gate_name: &str, id: Option<&str>) -> Option<StatsigError> {
+        Self::use_client(|client| {
+            client.remove_gate_override(gate_name, id);
+            Ok(())
+        })
+        .err()
+    }
+
+    pub fn remove_config_override(config_name: &str, id: Option<&str>) -> Option<StatsigError> {
+        Self::use_client(|client| {
+            client.remove_config_override(config_name, id);
+            Ok(())
+        })
+        .err()
+    }
+
+    pub fn remove_layer_override(layer_name: &str, id: Option<&str>) -> Option<StatsigError> {
+        Self::use_client(|client| {
+            client.remove_layer_override(layer_name, id);
+            Ok(())
+        })
+        .err()
+    }
+
+    pub fn remove_all_overrides() -> Option<StatsigError> {
+        Self::use_client(|client| {
+            client.remove_all_overrides();
+            Ok(())
+        })
+        .err()
+    }
+
+    fn use_client<T>(
+        func: impl FnOnce(&StatsigClient) -> Result<T, StatsigError>,
     ) -> Result<T, StatsigError> {
-        if let Ok(guard) = DRIVER.read() {
-            if let Some(driver) = guard.deref() {
-                return func(driver);
+        if let Ok(guard) = CLIENT.read() {
+            if let Some(client) = guard.deref() {
+                return func(client);
             }
             return Err(StatsigError::Uninitialized);
         }
@@ -171,13 +370,9 @@ impl Statsig {
     #[doc(hidden)]
     #[cfg(statsig_kong)]
     pub async fn __unsafe_reset() {
-        if let Some(mut guard) = DRIVER.write().ok() {
-            if let Some(driver) = guard.take() {
-                let _ = spawn_blocking(move || {
-                    driver.shutdown();
-                })
-                .await;
-            }
+        let client = CLIENT.write().ok().and_then(|mut guard| guard.take());
+        if let Some(client) = client {
+            let _ = client.shutdown().await;
         }
     }
 }
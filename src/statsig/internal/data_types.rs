@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use super::evaluation::CompiledCondition;
 
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -45,6 +48,25 @@ pub struct APICondition {
     pub field: Option<String>,
     pub additional_values: Option<HashMap<String, Value>>,
     pub id_type: String,
+    #[serde(skip)]
+    compiled: OnceLock<CompiledCondition>,
+}
+
+impl APISpec {
+    // Called when specs are synced so evaluations never compile conditions
+    pub fn compile_conditions(&self) {
+        for rule in &self.rules {
+            for condition in &rule.conditions {
+                condition.compiled();
+            }
+        }
+    }
+}
+
+impl APICondition {
+    pub fn compiled(&self) -> &CompiledCondition {
+        self.compiled.get_or_init(|| CompiledCondition::new(self))
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -58,11 +80,23 @@ pub struct APIDownloadedConfigsWithUpdates {
     pub time: u64,
 }
 
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct APIIDListMetadata {
+    pub size: u64,
+    pub url: Option<String>,
+    pub creation_time: u64,
+    #[serde(rename = "fileID")]
+    pub file_id: Option<String>,
+}
+
+#[allow(dead_code)]
 #[derive(Deserialize)]
 pub struct APIDownloadedConfigsNoUpdates {
     pub has_updates: bool,
 }
 
+#[allow(dead_code)]
 #[derive(Deserialize)]
 #[serde(untagged)]
 pub enum APIDownloadedConfigsResponse {
@@ -1,4 +1,7 @@
+use std::collections::HashMap;
+
 use serde::de::DeserializeOwned;
+use serde_json::Value;
 
 use super::evaluation::eval_details::EvalDetails;
 
@@ -6,5 +9,84 @@ pub struct DynamicConfig<T: DeserializeOwned> {
     pub name: String,
     pub value: Option<T>,
     pub rule_id: String,
-    pub evaluation_details: EvalDetails
+    pub evaluation_details: EvalDetails,
+}
+
+// Getters for single parameters of an untyped config, fetched with
+// get_config::<Value>. The exposure was already logged when the config was
+// fetched. The typed getters return None if the parameter is missing or has a
+// different type.
+impl DynamicConfig<Value> {
+    pub fn get_value(&self, key: &str) -> Option<Value> {
+        self.parameter(key).cloned()
+    }
+
+    pub fn get_string(&self, key: &str) -> Option<String> {
+        self.parameter(key)?.as_str().map(str::to_string)
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        self.parameter(key)?.as_bool()
+    }
+
+    pub fn get_f64(&self, key: &str) -> Option<f64> {
+        self.parameter(key)?.as_f64()
+    }
+
+    pub fn get_i64(&self, key: &str) -> Option<i64> {
+        self.parameter(key)?.as_i64()
+    }
+
+    pub fn get_array(&self, key: &str) -> Option<Vec<Value>> {
+        self.parameter(key)?.as_array().cloned()
+    }
+
+    pub fn get_object(&self, key: &str) -> Option<HashMap<String, Value>> {
+        let object = self.parameter(key)?.as_object()?;
+        Some(object.clone().into_iter().collect())
+    }
+
+    pub fn keys(&self) -> Vec<&str> {
+        match self.value.as_ref().and_then(Value::as_object) {
+            Some(object) => object.keys().map(String::as_str).collect(),
+            None => vec![],
+        }
+    }
+
+    fn parameter(&self, key: &str) -> Option<&Value> {
+        self.value.as_ref()?.as_object()?.get(key)
+    }
+}
+
+#[tokio::test]
+async fn test_typed_parameter_getters() {
+    use serde_json::json;
+
+    use crate::statsig::internal::test_utils::{make_config_spec, make_specs, serve_specs};
+    use crate::statsig::internal::StatsigDriver;
+    use crate::StatsigUser;
+
+    let parameters =
+        json!({"s": "text", "b": true, "n": 1.5, "i": 2, "arr": [1], "obj": {"k": "v"}});
+    let configs = vec![make_config_spec("a_config", "dynamic_config", parameters)];
+    let (options, _) = serve_specs(make_specs(vec![], configs, vec![], 1));
+    let driver = StatsigDriver::new("secret key", options).unwrap();
+    driver.initialize().await;
+    let user = StatsigUser::with_user_id("a-user".to_string());
+
+    let config = driver.get_config::<Value>(&user, "a_config");
+    assert_eq!(config.get_string("s"), Some("text".to_string()));
+    assert_eq!(config.get_bool("s"), None);
+    assert_eq!(config.get_bool("b"), Some(true));
+    assert_eq!(config.get_f64("n"), Some(1.5));
+    assert_eq!(config.get_i64("i"), Some(2));
+    assert_eq!(config.get_array("arr"), Some(vec![json!(1)]));
+    assert_eq!(
+        config.get_object("obj"),
+        Some(HashMap::from([("k".to_string(), json!("v"))]))
+    );
+    assert_eq!(config.get_value("missing"), None);
+    let mut keys = config.keys();
+    keys.sort();
+    assert_eq!(keys, vec!["arr", "b", "i", "n", "obj", "s"]);
 }
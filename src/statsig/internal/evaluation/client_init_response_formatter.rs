@@ -1,13 +1,10 @@
 use std::collections::HashMap;
 
-use base64;
-use base64::prelude::BASE64_STANDARD;
-use base64::Engine;
 use serde_json::Value::Null;
 use serde_json::{json, Value};
-use sha2::{Digest, Sha256};
 
 use crate::statsig::internal::data_types::APISpec;
+use crate::statsig::internal::evaluation::eval_helpers::hash_name;
 use crate::statsig::internal::statsig_store::StatsigStore;
 use crate::statsig::internal::EvalResult;
 use crate::{unwrap_or_noop, unwrap_or_return, StatsigUser};
@@ -22,7 +19,7 @@ impl ClientInitResponseFormatter {
         user: &StatsigUser,
         spec_store: &StatsigStore,
     ) -> Value {
-        let specs = unwrap_or_return!(spec_store.specs.read().ok(), Null);
+        let specs = spec_store.specs.load();
 
         let get_evaluated_spec = |spec_name, spec| -> Option<Value> {
             let eval_result = eval_func(user, spec);
@@ -51,7 +48,7 @@ impl ClientInitResponseFormatter {
                         "is_device_based".into(),
                         json!(spec.id_type.to_lowercase() == "stableid"),
                     );
-                    if !eval_result.group_name.is_none() {
+                    if eval_result.group_name.is_some() {
                         result.insert("group_name".into(), json!(eval_result.group_name));
                     }
                     if spec.entity.as_str() != "layer" {
@@ -113,13 +110,6 @@ impl ClientInitResponseFormatter {
     }
 }
 
-fn hash_name(name: &str) -> String {
-    let mut hash = Sha256::new();
-    hash.update(name.as_bytes());
-
-    BASE64_STANDARD.encode(hash.finalize())
-}
-
 fn clean_exposures(_exposures: &SecondaryExposures) -> SecondaryExposures {
     Some(vec![])
 }
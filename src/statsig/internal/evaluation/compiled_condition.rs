@@ -0,0 +1,252 @@
+use std::collections::HashSet;
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::statsig::internal::data_types::APICondition;
+
+use super::eval_helpers::{parse_version, value_to_f64, value_to_i64, value_to_string};
+
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConditionType {
+    Public,
+    FailGate,
+    PassGate,
+    IpBased,
+    UaBased,
+    UserField,
+    EnvironmentField,
+    CurrentTime,
+    UserBucket,
+    UnitId,
+    #[default]
+    Unknown,
+}
+
+impl ConditionType {
+    fn from_name(name: &str) -> Self {
+        match name.to_lowercase().as_str() {
+            "public" => ConditionType::Public,
+            "fail_gate" => ConditionType::FailGate,
+            "pass_gate" => ConditionType::PassGate,
+            "ip_based" => ConditionType::IpBased,
+            "ua_based" => ConditionType::UaBased,
+            "user_field" => ConditionType::UserField,
+            "environment_field" => ConditionType::EnvironmentField,
+            "current_time" => ConditionType::CurrentTime,
+            "user_bucket" => ConditionType::UserBucket,
+            "unit_id" => ConditionType::UnitId,
+            _ => ConditionType::Unknown,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    VersionGt,
+    VersionGte,
+    VersionLt,
+    VersionLte,
+    VersionEq,
+    VersionNeq,
+    Any,
+    None,
+    AnyCaseSensitive,
+    NoneCaseSensitive,
+    StrStartsWithAny,
+    StrEndsWithAny,
+    StrContainsAny,
+    StrContainsNone,
+    StrMatches,
+    Before,
+    After,
+    On,
+    InSegmentList,
+    NotInSegmentList,
+    Eq,
+    Neq,
+}
+
+impl Operator {
+    fn from_name(name: &str) -> Option<Self> {
+        let operator = match name {
+            "gt" => Operator::Gt,
+            "gte" => Operator::Gte,
+            "lt" => Operator::Lt,
+            "lte" => Operator::Lte,
+            "version_gt" => Operator::VersionGt,
+            "version_gte" => Operator::VersionGte,
+            "version_lt" => Operator::VersionLt,
+            "version_lte" => Operator::VersionLte,
+            "version_eq" => Operator::VersionEq,
+            "version_neq" => Operator::VersionNeq,
+            "any" => Operator::Any,
+            "none" => Operator::None,
+            "any_case_sensitive" => Operator::AnyCaseSensitive,
+            "none_case_sensitive" => Operator::NoneCaseSensitive,
+            "str_starts_with_any" => Operator::StrStartsWithAny,
+            "str_ends_with_any" => Operator::StrEndsWithAny,
+            "str_contains_any" => Operator::StrContainsAny,
+            "str_contains_none" => Operator::StrContainsNone,
+            "str_matches" => Operator::StrMatches,
+            "before" => Operator::Before,
+            "after" => Operator::After,
+            "on" => Operator::On,
+            "in_segment_list" => Operator::InSegmentList,
+            "not_in_segment_list" => Operator::NotInSegmentList,
+            "eq" => Operator::Eq,
+            "neq" => Operator::Neq,
+            _ => return None,
+        };
+        Some(operator)
+    }
+}
+
+// An APICondition parsed ahead of time, so evaluating it doesn't need to
+// match strings, compile regexes or re-parse the target value.
+// Only the target fields used by the operator are filled in.
+#[derive(Clone, Default)]
+pub struct CompiledCondition {
+    pub condition_type: ConditionType,
+    // None if missing or unknown
+    pub operator: Option<Operator>,
+    pub target_value: Value,
+    // Gate name for pass_gate/fail_gate, list name for segment lists
+    pub target_str: String,
+    pub target_number: Option<f64>,
+    pub target_time: Option<i64>,
+    pub target_version: Vec<Option<i32>>,
+    // None if the target is not an array. Lowercased for case insensitive
+    // operators.
+    pub target_strings: Option<Vec<String>>,
+    pub target_set: Option<HashSet<String>>,
+    pub regex: Option<Regex>,
+    pub user_bucket_salt: String,
+}
+
+impl CompiledCondition {
+    pub fn new(condition: &APICondition) -> Self {
+        let target_value = condition.target_value.clone().unwrap_or(Value::Null);
+        let condition_type = ConditionType::from_name(&condition.condition_type);
+        let operator = condition.operator.as_deref().and_then(Operator::from_name);
+
+        let mut compiled = CompiledCondition {
+            condition_type,
+            operator,
+            ..CompiledCondition::default()
+        };
+
+        if matches!(
+            condition_type,
+            ConditionType::PassGate | ConditionType::FailGate
+        ) {
+            compiled.target_str = value_to_string(&target_value).unwrap_or_default();
+        }
+
+        if condition_type == ConditionType::UserBucket {
+            if let Some(Value::String(salt)) = condition
+                .additional_values
+                .as_ref()
+                .and_then(|values| values.get("salt"))
+            {
+                compiled.user_bucket_salt = salt.clone();
+            }
+        }
+
+        match operator {
+            Some(Operator::Gt | Operator::Gte | Operator::Lt | Operator::Lte) => {
+                compiled.target_number = value_to_f64(&target_value);
+            }
+            Some(
+                Operator::VersionGt
+                | Operator::VersionGte
+                | Operator::VersionLt
+                | Operator::VersionLte
+                | Operator::VersionEq
+                | Operator::VersionNeq,
+            ) => {
+                compiled.target_version = parse_version(&target_value);
+            }
+            Some(Operator::Any | Operator::None) => {
+                compiled.target_set = get_target_strings(&target_value, true)
+                    .map(|strings| strings.into_iter().collect());
+            }
+            Some(Operator::AnyCaseSensitive | Operator::NoneCaseSensitive) => {
+                compiled.target_set = get_target_strings(&target_value, false)
+                    .map(|strings| strings.into_iter().collect());
+            }
+            Some(
+                Operator::StrStartsWithAny
+                | Operator::StrEndsWithAny
+                | Operator::StrContainsAny
+                | Operator::StrContainsNone,
+            ) => {
+                compiled.target_strings = get_target_strings(&target_value, true);
+            }
+            Some(Operator::StrMatches) => {
+                compiled.regex =
+                    value_to_string(&target_value).and_then(|pattern| Regex::new(&pattern).ok());
+            }
+            Some(Operator::Before | Operator::After | Operator::On) => {
+                compiled.target_time = value_to_i64(&target_value);
+            }
+            Some(Operator::InSegmentList | Operator::NotInSegmentList) => {
+                compiled.target_str = value_to_string(&target_value).unwrap_or_default();
+            }
+            _ => {}
+        }
+
+        compiled.target_value = target_value;
+        compiled
+    }
+}
+
+fn get_target_strings(target_value: &Value, ignore_case: bool) -> Option<Vec<String>> {
+    let strings = target_value
+        .as_array()?
+        .iter()
+        .filter_map(value_to_string)
+        .map(|s| if ignore_case { s.to_lowercase() } else { s })
+        .collect();
+    Some(strings)
+}
+
+#[test]
+fn test_compiled_condition() {
+    use super::eval_helpers::{compare_strings_in_set, compare_versions};
+    use serde_json::json;
+
+    let condition: APICondition = serde_json::from_value(json!({
+        "type": "User_Field",
+        "targetValue": ["Foo", "BAR", 3],
+        "operator": "none",
+        "field": "email",
+        "idType": "userID"
+    }))
+    .unwrap();
+    let compiled = CompiledCondition::new(&condition);
+    assert!(compiled.condition_type == ConditionType::UserField);
+    assert!(compiled.operator == Some(Operator::None));
+    let set = compiled.target_set.as_ref();
+    assert!(!compare_strings_in_set(&json!("bar"), set, Operator::None));
+    assert!(!compare_strings_in_set(&json!(3), set, Operator::None));
+    assert!(compare_strings_in_set(&json!("baz"), set, Operator::None));
+
+    let version = parse_version(&json!("1.2.x-beta"));
+    assert_eq!(
+        compare_versions(&json!("1.1.9"), &version, Operator::VersionLt),
+        Some(true)
+    );
+    assert_eq!(
+        compare_versions(&json!("1.2.0"), &version, Operator::VersionLt),
+        None
+    );
+    assert_eq!(
+        compare_versions(&json!("2-rc1"), &version, Operator::VersionGt),
+        Some(true)
+    );
+}
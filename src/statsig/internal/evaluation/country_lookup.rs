@@ -1,21 +1,48 @@
+use std::net::IpAddr;
+
 use serde_json::Value;
 use serde_json::Value::Null;
 
 use crate::statsig::internal::helpers::UsizeExt;
 use crate::StatsigUser;
 
+// Ranges are keyed on the 128-bit address. IPv4 ranges live in the
+// IPv4-mapped block (::ffff:0:0/96) and native IPv6 ranges come after it, so
+// one sorted table covers both. IPv6 addresses that embed an IPv4 address
+// (IPv4-mapped or 6to4) are looked up by that address.
+const IPV4_MAPPED_BASE: u128 = 0xffff << 32;
+
 pub struct CountryLookup {
     country_codes: Vec<String>,
-    ip_ranges: Vec<i64>,
+    ip_ranges: Vec<u128>,
 }
 
 impl CountryLookup {
     pub fn new() -> Self {
-        let bytes = include_bytes!("resources/ip_supalite.table");
+        // Anything below the IPv4 block is unknown
+        let mut lookup = Self {
+            country_codes: vec!["--".to_string()],
+            ip_ranges: vec![IPV4_MAPPED_BASE],
+        };
+
+        // IPv4 ranges are counted in /24s
+        lookup.load_table(
+            include_bytes!("resources/ip_supalite.table"),
+            IPV4_MAPPED_BASE,
+            8,
+        );
+        // IPv6 ranges are counted in /48s. The data is from the IPFire
+        // Location database (CC BY-SA 4.0), https://location.ipfire.org/
+        lookup.load_table(include_bytes!("resources/ip6_supalite.table"), 0, 80);
+
+        lookup
+    }
 
+    // A table lists its country codes, ending with "**", then the size and
+    // country code index of each consecutive range starting from `base`.
+    // Sizes are in units of 2^unit_bits addresses.
+    fn load_table(&mut self, bytes: &[u8], base: u128, unit_bits: u32) {
         let mut raw_code_lookup: Vec<String> = vec![];
-        let mut country_codes: Vec<String> = vec![];
-        let mut ip_ranges: Vec<i64> = vec![];
 
         let mut i = 0;
 
@@ -30,35 +57,25 @@ impl CountryLookup {
             }
         }
 
-        let longs = |index: usize| bytes[index] as i64;
-
-        let mut last_end_range = 0_i64;
+        let mut last_end_range = base;
         while (i + 1) < bytes.len() {
-            let mut count: i64 = 0;
-            let n1 = longs(i.post_inc());
-            if n1 < 240 {
-                count = n1;
-            } else if n1 == 242 {
-                let n2 = longs(i.post_inc());
-                let n3 = longs(i.post_inc());
-                count = n2 | (n3 << 8);
-            } else if n1 == 243 {
-                let n2 = longs(i.post_inc());
-                let n3 = longs(i.post_inc());
-                let n4 = longs(i.post_inc());
-                count = n2 | (n3 << 8) | (n4 << 16);
+            // Sizes under 240 take one byte. Otherwise the first byte is 240
+            // plus the number of little-endian bytes that follow.
+            let mut count = bytes[i.post_inc()] as u128;
+            if count >= 240 {
+                let width = count as usize - 240;
+                count = bytes[i..i + width]
+                    .iter()
+                    .rev()
+                    .fold(0, |acc, byte| (acc << 8) | *byte as u128);
+                i += width;
             }
 
-            last_end_range += count * 256;
+            last_end_range += count << unit_bits;
 
             let cc = bytes[i.post_inc()] as usize;
-            ip_ranges.push(last_end_range);
-            country_codes.push(raw_code_lookup[cc].clone())
-        }
-
-        Self {
-            country_codes,
-            ip_ranges,
+            self.ip_ranges.push(last_end_range);
+            self.country_codes.push(raw_code_lookup[cc].clone())
         }
     }
 
@@ -84,30 +101,35 @@ impl CountryLookup {
     }
 
     fn lookup(&self, ip_address: &str) -> Option<String> {
-        let parts: Vec<&str> = ip_address.split('.').collect();
-        if parts.len() != 4 {
-            return None;
-        }
-
-        let nums: Vec<Option<i64>> = parts.iter().map(|&x| x.parse().ok()).collect();
-        if let (Some(n0), Some(n1), Some(n2), Some(n3)) = (nums[0], nums[1], nums[2], nums[3]) {
-            let ip_number = (n0 * 256_i64.pow(3)) + (n1 << 16) + (n2 << 8) + n3;
-            return self.lookup_numeric(ip_number);
-        }
+        let ip_number = match ip_address.trim().parse::<IpAddr>().ok()? {
+            IpAddr::V4(v4) => IPV4_MAPPED_BASE | u32::from(v4) as u128,
+            IpAddr::V6(v6) => {
+                let segments = v6.segments();
+                match v6.to_ipv4_mapped() {
+                    Some(v4) => IPV4_MAPPED_BASE | u32::from(v4) as u128,
+                    // 6to4 (2002::/16) embeds the IPv4 address in the prefix
+                    None if segments[0] == 0x2002 => {
+                        let v4 = ((segments[1] as u32) << 16) | segments[2] as u32;
+                        IPV4_MAPPED_BASE | v4 as u128
+                    }
+                    None => u128::from(v6),
+                }
+            }
+        };
 
-        None
+        self.lookup_numeric(ip_number)
     }
 
-    fn lookup_numeric(&self, ip_address: i64) -> Option<String> {
+    fn lookup_numeric(&self, ip_address: u128) -> Option<String> {
         let index = self.binary_search(ip_address);
-        let cc = self.country_codes[index].clone();
+        let cc = self.country_codes.get(index)?.clone();
         if cc == "--" {
             return None;
         }
         Some(cc)
     }
 
-    fn binary_search(&self, value: i64) -> usize {
+    fn binary_search(&self, value: u128) -> usize {
         let mut min = 0;
         let mut max = self.ip_ranges.len();
 
@@ -123,3 +145,25 @@ impl CountryLookup {
         min
     }
 }
+
+#[test]
+fn test_lookup_ipv4_and_ipv6() {
+    let lookup = CountryLookup::new();
+
+    let country = lookup.lookup("1.1.1.1");
+    assert!(country.is_some());
+    assert_eq!(lookup.lookup("::ffff:1.1.1.1"), country);
+    assert_eq!(lookup.lookup("::ffff:101:101"), country);
+    assert_eq!(lookup.lookup("2002:101:101::1"), country);
+
+    assert_eq!(lookup.lookup("::1"), None);
+    assert_eq!(lookup.lookup("2001:db8::1"), None);
+    assert_eq!(lookup.lookup("1.1.1"), None);
+
+    // Native IPv6 addresses resolve through the IPv6 ranges
+    assert_eq!(
+        lookup.lookup("2001:4860:4860::8888"),
+        Some("US".to_string())
+    );
+    assert_eq!(lookup.lookup("2a00:1450:4001::1"), Some("IE".to_string()));
+}
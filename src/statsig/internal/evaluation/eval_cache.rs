@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use super::eval_result::EvalResult;
+
+// Gate and config results for a single user, reused across calls and nested
+// gate checks. Entries are dropped whenever the specs are re-synced.
+pub struct EvalCache {
+    entries: Mutex<CacheEntries>,
+}
+
+struct CacheEntries {
+    sync_time: u64,
+    gates: HashMap<String, EvalResult>,
+    configs: HashMap<String, EvalResult>,
+}
+
+impl EvalCache {
+    pub fn new() -> Self {
+        EvalCache {
+            entries: Mutex::new(CacheEntries {
+                sync_time: 0,
+                gates: HashMap::new(),
+                configs: HashMap::new(),
+            }),
+        }
+    }
+
+    pub fn get_gate(&self, gate_name: &str, sync_time: u64) -> Option<EvalResult> {
+        let mut entries = self.entries.lock().ok()?;
+        entries.invalidate_if_stale(sync_time);
+        entries.gates.get(gate_name).cloned()
+    }
+
+    pub fn get_config(&self, config_name: &str, sync_time: u64) -> Option<EvalResult> {
+        let mut entries = self.entries.lock().ok()?;
+        entries.invalidate_if_stale(sync_time);
+        entries.configs.get(config_name).cloned()
+    }
+
+    pub fn set_gate(&self, gate_name: &str, sync_time: u64, result: &EvalResult) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.invalidate_if_stale(sync_time);
+            entries.gates.insert(gate_name.to_string(), result.clone());
+        }
+    }
+
+    pub fn set_config(&self, config_name: &str, sync_time: u64, result: &EvalResult) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.invalidate_if_stale(sync_time);
+            entries
+                .configs
+                .insert(config_name.to_string(), result.clone());
+        }
+    }
+}
+
+impl CacheEntries {
+    fn invalidate_if_stale(&mut self, sync_time: u64) {
+        if self.sync_time != sync_time {
+            self.sync_time = sync_time;
+            self.gates.clear();
+            self.configs.clear();
+        }
+    }
+}
+
+#[test]
+fn test_eval_cache_invalidates_on_sync() {
+    let cache = EvalCache::new();
+    cache.set_gate("a_gate", 1, &EvalResult::boolean(true));
+    assert!(cache.get_gate("a_gate", 1).is_some_and(|r| r.bool_value));
+    assert!(cache.get_config("a_gate", 1).is_none());
+
+    assert!(cache.get_gate("a_gate", 2).is_none());
+    assert!(cache.get_gate("a_gate", 1).is_none());
+}
@@ -9,8 +9,8 @@ pub struct EvalDetails {
     pub server_time: u64,
 }
 
-impl EvalDetails {
-    pub fn default() -> Self {
+impl Default for EvalDetails {
+    fn default() -> Self {
         EvalDetails {
             reason: EvaluationReason::Uninitialized,
             config_sync_time: 0,
@@ -23,7 +23,15 @@ impl EvalDetails {
 pub enum EvaluationReason {
     Network,
     DataAdapter,
+    Bootstrap,
     Uninitialized,
+    // Initialized with local_mode, so no specs were loaded
+    LocalMode,
     Unrecognized,
     Unsupported,
+    LocalOverride,
+    // Served from a UserPersistentStorage assignment
+    Persisted,
+    // A ua_based condition was hit before the user agent parser loaded
+    UserAgentParserNotReady,
 }
@@ -1,16 +1,26 @@
-use std::cmp::max;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::mem::size_of;
 
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
 use chrono::Duration;
 use regex::Regex;
 use serde_json::Value;
 use sha2::{Digest, Sha256};
 
-use crate::statsig::internal::helpers::UsizeExt;
+use super::compiled_condition::Operator;
 
-pub fn compute_user_hash(value: String) -> Option<usize> {
+// Hashes the parts joined with '.', without building the joined string
+pub fn compute_user_hash(parts: &[&str]) -> Option<usize> {
     let mut sha256 = Sha256::new();
-    sha256.update(value.as_str().as_bytes());
+    for (i, part) in parts.iter().enumerate() {
+        if i > 0 {
+            sha256.update(b".");
+        }
+        sha256.update(part.as_bytes());
+    }
     let result = sha256.finalize();
     match result.split_at(size_of::<usize>()).0.try_into() {
         Ok(bytes) => Some(usize::from_be_bytes(bytes)),
@@ -18,129 +28,131 @@ pub fn compute_user_hash(value: String) -> Option<usize> {
     }
 }
 
-pub fn compare_numbers(left: &Value, right: &Value, op: &str) -> Option<bool> {
+pub fn hash_name(name: &str) -> String {
+    let mut hash = Sha256::new();
+    hash.update(name.as_bytes());
+
+    BASE64_STANDARD.encode(hash.finalize())
+}
+
+pub fn compare_numbers(left: &Value, right: Option<f64>, op: Operator) -> Option<bool> {
     let left_num = value_to_f64(left)?;
-    let right_num = value_to_f64(right)?;
+    let right_num = right?;
     match op {
-        "gt" => Some(left_num > right_num),
-        "gte" => Some(left_num >= right_num),
-        "lt" => Some(left_num < right_num),
-        "lte" => Some(left_num <= right_num),
+        Operator::Gt => Some(left_num > right_num),
+        Operator::Gte => Some(left_num >= right_num),
+        Operator::Lt => Some(left_num < right_num),
+        Operator::Lte => Some(left_num <= right_num),
         _ => None,
     }
 }
 
-pub fn compare_versions(left: &Value, right: &Value, op: &str) -> Option<bool> {
-    let mut left_str = value_to_string(left)?;
-    let mut right_str = value_to_string(right)?;
-
-    if let Some(index) = left_str.find('-') {
-        left_str = left_str[0..index].to_string();
-    }
-
-    if let Some(index) = right_str.find('-') {
-        right_str = right_str[0..index].to_string();
-    }
-
-    fn comparison(left_str: &str, right_str: &str) -> Option<i32> {
-        let left_parts: Vec<&str> = left_str.split('.').collect();
-        let right_parts: Vec<&str> = right_str.split('.').collect();
-
-        let mut i = 0;
-        while i < max(left_parts.len(), right_parts.len()) {
-            let (mut left_count, mut right_count) = (0, 0);
+// Splits a version like "1.2.3-beta" into its numeric parts. Parts that
+// aren't numbers are kept as None so they only fail the comparison if reached.
+pub fn parse_version(value: &Value) -> Vec<Option<i32>> {
+    with_value_str(value, |version| {
+        let version = version.split('-').next().unwrap_or_default();
+        version.split('.').map(|part| part.parse().ok()).collect()
+    })
+}
 
-            if i < left_parts.len() {
-                left_count = left_parts[i].parse().ok()?;
-            }
+pub fn compare_versions(left: &Value, right: &[Option<i32>], op: Operator) -> Option<bool> {
+    with_value_str(left, |left_str| compare_version_str(left_str, right, op))
+}
 
-            if i < right_parts.len() {
-                right_count = right_parts[i].parse().ok()?;
-            }
+fn compare_version_str(left_str: &str, right: &[Option<i32>], op: Operator) -> Option<bool> {
+    let left_str = left_str.split('-').next().unwrap_or_default();
 
-            if left_count < right_count {
-                return Some(-1);
-            }
+    let mut left_parts = left_str.split('.');
+    let mut right_parts = right.iter();
+    let result = loop {
+        let (left_part, right_part) = match (left_parts.next(), right_parts.next()) {
+            (None, None) => break Ordering::Equal,
+            parts => parts,
+        };
 
-            if left_count > right_count {
-                return Some(1);
-            }
+        let left_count: i32 = match left_part {
+            Some(part) => part.parse().ok()?,
+            None => 0,
+        };
+        let right_count = match right_part {
+            Some(part) => (*part)?,
+            None => 0,
+        };
 
-            i.post_inc();
+        match left_count.cmp(&right_count) {
+            Ordering::Equal => continue,
+            ordering => break ordering,
         }
-        Some(0)
-    }
+    };
 
-    let result = comparison(&left_str, &right_str)?;
     match op {
-        "version_gt" => Some(result > 0),
-        "version_gte" => Some(result >= 0),
-        "version_lt" => Some(result < 0),
-        "version_lte" => Some(result <= 0),
-        "version_eq" => Some(result == 0),
-        "version_neq" => Some(result != 0),
+        Operator::VersionGt => Some(result.is_gt()),
+        Operator::VersionGte => Some(result.is_ge()),
+        Operator::VersionLt => Some(result.is_lt()),
+        Operator::VersionLte => Some(result.is_le()),
+        Operator::VersionEq => Some(result.is_eq()),
+        Operator::VersionNeq => Some(result.is_ne()),
         _ => None,
     }
 }
 
-pub fn compare_strings_in_array(value: &Value, array: &Value, op: &str, ignore_case: bool) -> bool {
-    let comparison = || {
-        let value_str = value_to_string(value)?;
-        Some(array.as_array()?.iter().any(|current| {
-            let curr_str = match value_to_string(current) {
-                Some(s) => s,
-                _ => return false,
-            };
-            let left = if ignore_case {
-                value_str.to_lowercase()
-            } else {
-                value_str.clone()
-            };
-            let right = if ignore_case {
-                curr_str.to_lowercase()
-            } else {
-                curr_str.clone()
-            };
+// For any, none and their case sensitive variants. The set is already
+// lowercased for the case insensitive ones.
+pub fn compare_strings_in_set(value: &Value, set: Option<&HashSet<String>>, op: Operator) -> bool {
+    let res = with_value_str(value, |value_str| match (op, set) {
+        (Operator::Any | Operator::None, Some(set)) => set.contains(lowercase(value_str).as_ref()),
+        (_, Some(set)) => set.contains(value_str),
+        (_, None) => false,
+    });
+
+    if op == Operator::None || op == Operator::NoneCaseSensitive {
+        return !res;
+    }
+    res
+}
 
-            match op {
-                "any" | "none" | "any_case_sensitive" | "none_case_sensitive" => left.eq(&right),
-                "str_starts_with_any" => left.starts_with(&right),
-                "str_ends_with_any" => left.ends_with(&right),
-                "str_contains_any" | "str_contains_none" => left.contains(&right),
+// For the str_*_any and str_contains_none operators, which are case
+// insensitive. The targets are already lowercased.
+pub fn compare_strings_in_array(value: &Value, array: Option<&[String]>, op: Operator) -> bool {
+    let res = match array {
+        Some(array) => with_value_str(value, |value_str| {
+            let left = lowercase(value_str);
+            array.iter().any(|right| match op {
+                Operator::StrStartsWithAny => left.starts_with(right.as_str()),
+                Operator::StrEndsWithAny => left.ends_with(right.as_str()),
+                Operator::StrContainsAny | Operator::StrContainsNone => {
+                    left.contains(right.as_str())
+                }
                 _ => false,
-            }
-        }))
+            })
+        }),
+        None => false,
     };
 
-    let res = comparison().unwrap_or(false);
-
-    if op == "none" || op == "none_case_sensitive" || op == "str_contains_none" {
+    if op == Operator::StrContainsNone {
         return !res;
     }
     res
 }
 
-pub fn compare_str_with_regex(value: &Value, regex_value: &Value) -> bool {
-    let comparison = || {
-        let value_str = value_to_string(value)?;
-        let regex_str = value_to_string(regex_value)?;
-        let regex = Regex::new(&regex_str).ok()?;
-        Some(regex.is_match(&value_str))
-    };
-
-    comparison().unwrap_or(false)
+pub fn compare_str_with_regex(value: &Value, regex: Option<&Regex>) -> bool {
+    match regex {
+        Some(regex) => with_value_str(value, |value_str| regex.is_match(value_str)),
+        None => false,
+    }
 }
 
-pub fn compare_time(left: &Value, right: &Value, op: &str) -> Option<bool> {
+pub fn compare_time(left: &Value, right: Option<i64>, op: Operator) -> Option<bool> {
     let raw_left = value_to_i64(left)?;
-    let right_num = value_to_i64(right)?;
+    let right_num = right?;
     
     let left_num = to_millis(raw_left);
 
     match op {
-        "before" => Some(left_num < right_num),
-        "after" => Some(left_num > right_num),
-        "on" => Some(
+        Operator::Before => Some(left_num < right_num),
+        Operator::After => Some(left_num > right_num),
+        Operator::On => Some(
             Duration::milliseconds(left_num).num_days()
                 == Duration::milliseconds(right_num).num_days(),
         ),
@@ -164,6 +176,32 @@ pub fn value_to_i64(value: &Value) -> Option<i64> {
     }
 }
 
+// Calls `f` with the value as it would be printed, without the quotes for
+// strings. Numbers are printed to a stack buffer, so only arrays and objects
+// allocate.
+pub fn with_value_str<R>(value: &Value, f: impl FnOnce(&str) -> R) -> R {
+    match value {
+        Value::String(s) => f(s),
+        Value::Bool(b) => f(if *b { "true" } else { "false" }),
+        Value::Null => f("null"),
+        Value::Number(n) => {
+            const BUF_LEN: usize = 32;
+            let mut buf = [0u8; BUF_LEN];
+            let len = {
+                let mut cursor = &mut buf[..];
+                serde_json::to_writer(&mut cursor, n)
+                    .ok()
+                    .map(|_| BUF_LEN - cursor.len())
+            };
+            match len.and_then(|len| std::str::from_utf8(&buf[..len]).ok()) {
+                Some(s) => f(s),
+                None => f(&n.to_string()),
+            }
+        }
+        _ => f(&value.to_string()),
+    }
+}
+
 pub fn value_to_string(value: &Value) -> Option<String> {
     match value {
         Value::String(s) => Some(s.clone()),
@@ -171,6 +209,15 @@ pub fn value_to_string(value: &Value) -> Option<String> {
     }
 }
 
+// Only allocates if the string has uppercase or non-ascii characters
+fn lowercase(value: &str) -> Cow<'_, str> {
+    if value.is_ascii() && !value.bytes().any(|b| b.is_ascii_uppercase()) {
+        Cow::Borrowed(value)
+    } else {
+        Cow::Owned(value.to_lowercase())
+    }
+}
+
 fn to_millis(ts: i64) -> i64 {
     let a = ts.abs();
     if a < 10_000_000_000 {        
@@ -180,3 +227,30 @@ fn to_millis(ts: i64) -> i64 {
     }
 }
 
+
+#[test]
+fn test_hashing_and_printing_match_the_joined_strings() {
+    use serde_json::json;
+
+    let joined = |value: &str| {
+        let result = Sha256::digest(value.as_bytes());
+        usize::from_be_bytes(result[..size_of::<usize>()].try_into().unwrap())
+    };
+    assert_eq!(
+        compute_user_hash(&["salt", "rule", "user"]),
+        Some(joined("salt.rule.user"))
+    );
+    assert_eq!(compute_user_hash(&["salt", ""]), Some(joined("salt.")));
+
+    for value in [
+        json!(3),
+        json!(-7),
+        json!(1.0),
+        json!(1e20),
+        json!(true),
+        json!(null),
+    ] {
+        assert_eq!(with_value_str(&value, str::to_string), value.to_string());
+    }
+    assert_eq!(with_value_str(&json!("a"), str::to_string), "a");
+}
@@ -2,7 +2,9 @@ use serde_json::Value;
 use std::collections::HashMap;
 
 use super::eval_details::{EvalDetails, EvaluationReason};
+use crate::StickyValues;
 
+#[derive(Clone)]
 pub struct EvalResult {
     pub bool_value: bool,
     pub json_value: Option<Value>,
@@ -20,9 +22,17 @@ pub struct EvalResult {
 
 impl EvalResult {
     pub fn unsupported() -> Self {
+        Self::unsupported_with_reason(EvaluationReason::Unsupported)
+    }
+
+    pub fn unsupported_with_reason(reason: EvaluationReason) -> Self {
         Self {
             unsupported: true,
             rule_id: "unsupported".to_string(),
+            evaluation_details: EvalDetails {
+                reason,
+                ..EvalDetails::default()
+            },
             ..Self::default()
         }
     }
@@ -52,6 +62,39 @@ impl EvalResult {
         }
     }
 
+    pub fn local_override(
+        mut eval_details: EvalDetails,
+        bool_value: bool,
+        json_value: Value,
+    ) -> Self {
+        eval_details.reason = EvaluationReason::LocalOverride;
+        Self {
+            bool_value,
+            json_value: Some(json_value),
+            rule_id: "override".to_string(),
+            evaluation_details: eval_details,
+            ..Self::default()
+        }
+    }
+
+    pub fn persisted(mut eval_details: EvalDetails, sticky: StickyValues) -> Self {
+        eval_details.reason = EvaluationReason::Persisted;
+        Self {
+            bool_value: sticky.value,
+            json_value: sticky.json_value,
+            rule_id: sticky.rule_id,
+            secondary_exposures: Some(sticky.secondary_exposures),
+            undelegated_secondary_exposures: Some(sticky.undelegated_secondary_exposures),
+            explicit_parameters: sticky.explicit_parameters,
+            config_delegate: sticky.config_delegate,
+            is_experiment_group: true,
+            evaluation_details: eval_details,
+            group_name: sticky.group_name,
+            config_version: sticky.config_version,
+            ..Self::default()
+        }
+    }
+
     pub fn default() -> Self {
         Self {
             bool_value: false,
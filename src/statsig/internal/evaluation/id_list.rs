@@ -0,0 +1,124 @@
+use std::collections::HashSet;
+
+use crate::statsig::internal::data_types::APIIDListMetadata;
+
+pub struct IDList {
+    pub size: u64,
+    pub creation_time: u64,
+    pub file_id: Option<String>,
+    pub ids: HashSet<String>,
+}
+
+impl IDList {
+    pub fn new(metadata: &APIIDListMetadata) -> IDList {
+        IDList {
+            size: 0,
+            creation_time: metadata.creation_time,
+            file_id: metadata.file_id.clone(),
+            ids: HashSet::new(),
+        }
+    }
+
+    // Applies a downloaded chunk of "+id" / "-id" lines. Returns false if the
+    // chunk is malformed, in which case the list should be re-downloaded.
+    pub fn apply_changes(&mut self, changes: &str) -> bool {
+        if !changes.is_empty() && !changes.starts_with(['+', '-']) {
+            return false;
+        }
+
+        for line in changes.lines() {
+            let line = line.trim();
+            if line.len() < 2 {
+                continue;
+            }
+
+            let (op, id) = line.split_at(1);
+            match op {
+                "+" => {
+                    self.ids.insert(id.to_string());
+                }
+                "-" => {
+                    self.ids.remove(id);
+                }
+                _ => {}
+            }
+        }
+
+        self.size += changes.len() as u64;
+        true
+    }
+}
+
+#[test]
+fn test_id_list_apply_changes() {
+    let mut list = IDList::new(&APIIDListMetadata {
+        size: 0,
+        url: None,
+        creation_time: 1,
+        file_id: Some("file".to_string()),
+    });
+
+    assert!(list.apply_changes("+abc\n+def\n"));
+    assert!(list.apply_changes("-abc\n"));
+    assert!(!list.ids.contains("abc"));
+    assert!(list.ids.contains("def"));
+    assert_eq!(list.size, 15);
+
+    assert!(!list.apply_changes("<html>"));
+}
+
+#[tokio::test]
+async fn test_segment_list_condition() {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::eval_helpers::hash_name;
+    use crate::statsig::internal::test_utils::{make_gate_spec, make_specs, FakeNetworkProvider};
+    use crate::statsig::internal::StatsigDriver;
+    use crate::{NetworkEndpoint, NetworkRequest, StatsigOptions, StatsigUser};
+
+    let list_body = format!("+{}\n", &hash_name("listed-user")[..8]);
+    let list_size = list_body.len();
+    let specs = make_specs(
+        vec![make_gate_spec(
+            "segment_gate",
+            json!([{
+                "type": "unit_id",
+                "operator": "in_segment_list",
+                "targetValue": "a_segment",
+                "idType": "userID",
+            }]),
+        )],
+        vec![],
+        vec![],
+        1,
+    );
+
+    let handler = move |request: &NetworkRequest| match request.endpoint {
+        NetworkEndpoint::DownloadConfigSpecs => specs.clone(),
+        NetworkEndpoint::GetIdLists => json!({
+            "a_segment": {
+                "size": list_size,
+                "url": "https://example.com/a_segment",
+                "creationTime": 1,
+                "fileID": "file_1",
+            },
+        })
+        .to_string(),
+        NetworkEndpoint::DownloadIdList => list_body.clone(),
+        _ => "{}".to_string(),
+    };
+    let options = StatsigOptions {
+        network_provider: Some(Arc::new(FakeNetworkProvider(handler))),
+        ..StatsigOptions::default()
+    };
+    let driver = StatsigDriver::new("secret key", options).unwrap();
+    driver.initialize().await;
+
+    let listed = StatsigUser::with_user_id("listed-user".to_string());
+    assert!(driver.check_gate(&listed, "segment_gate"));
+
+    let other = StatsigUser::with_user_id("other-user".to_string());
+    assert!(!driver.check_gate(&other, "segment_gate"));
+}
@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use serde_json::Value;
+
+use crate::StatsigUser;
+
+// Overrides are keyed by spec name, then by the unit ID they are scoped to.
+// An empty unit ID applies the override to every user.
+type OverrideMap<T> = RwLock<HashMap<String, HashMap<String, T>>>;
+
+const ALL_USERS: &str = "";
+
+pub struct LocalOverrides {
+    gates: OverrideMap<bool>,
+    configs: OverrideMap<Value>,
+    layers: OverrideMap<Value>,
+}
+
+impl LocalOverrides {
+    pub fn new() -> Self {
+        LocalOverrides {
+            gates: RwLock::new(HashMap::new()),
+            configs: RwLock::new(HashMap::new()),
+            layers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn override_gate(&self, gate_name: &str, value: bool, id: Option<&str>) {
+        set_override(&self.gates, gate_name, value, id);
+    }
+
+    pub fn override_config(&self, config_name: &str, value: Value, id: Option<&str>) {
+        set_override(&self.configs, config_name, value, id);
+    }
+
+    pub fn override_layer(&self, layer_name: &str, value: Value, id: Option<&str>) {
+        set_override(&self.layers, layer_name, value, id);
+    }
+
+    pub fn remove_gate_override(&self, gate_name: &str, id: Option<&str>) {
+        remove_override(&self.gates, gate_name, id);
+    }
+
+    pub fn remove_config_override(&self, config_name: &str, id: Option<&str>) {
+        remove_override(&self.configs, config_name, id);
+    }
+
+    pub fn remove_layer_override(&self, layer_name: &str, id: Option<&str>) {
+        remove_override(&self.layers, layer_name, id);
+    }
+
+    pub fn remove_all_overrides(&self) {
+        clear_overrides(&self.gates);
+        clear_overrides(&self.configs);
+        clear_overrides(&self.layers);
+    }
+
+    pub fn get_gate_override(&self, user: &StatsigUser, gate_name: &str) -> Option<bool> {
+        get_override(&self.gates, user, gate_name)
+    }
+
+    pub fn get_config_override(&self, user: &StatsigUser, config_name: &str) -> Option<Value> {
+        get_override(&self.configs, user, config_name)
+    }
+
+    pub fn get_layer_override(&self, user: &StatsigUser, layer_name: &str) -> Option<Value> {
+        get_override(&self.layers, user, layer_name)
+    }
+}
+
+fn set_override<T>(map: &OverrideMap<T>, name: &str, value: T, id: Option<&str>) {
+    if let Ok(mut lock) = map.write() {
+        lock.entry(name.to_string())
+            .or_default()
+            .insert(id.unwrap_or(ALL_USERS).to_string(), value);
+    }
+}
+
+fn remove_override<T>(map: &OverrideMap<T>, name: &str, id: Option<&str>) {
+    if let Ok(mut lock) = map.write() {
+        if let Some(overrides) = lock.get_mut(name) {
+            overrides.remove(id.unwrap_or(ALL_USERS));
+            if overrides.is_empty() {
+                lock.remove(name);
+            }
+        }
+    }
+}
+
+fn clear_overrides<T>(map: &OverrideMap<T>) {
+    if let Ok(mut lock) = map.write() {
+        lock.clear();
+    }
+}
+
+fn get_override<T: Clone>(map: &OverrideMap<T>, user: &StatsigUser, name: &str) -> Option<T> {
+    let lock = map.read().ok()?;
+    let overrides = lock.get(name)?;
+
+    if let Some(user_id) = &user.user_id {
+        if let Some(value) = overrides.get(user_id) {
+            return Some(value.clone());
+        }
+    }
+
+    if let Some(custom_ids) = &user.custom_ids {
+        for custom_id in custom_ids.values() {
+            if let Some(value) = overrides.get(custom_id) {
+                return Some(value.clone());
+            }
+        }
+    }
+
+    overrides.get(ALL_USERS).cloned()
+}
+
+#[test]
+fn test_local_override_precedence() {
+    use serde_json::json;
+
+    let overrides = LocalOverrides::new();
+    overrides.override_gate("a_gate", false, None);
+    overrides.override_gate("a_gate", true, Some("a-user"));
+    overrides.override_gate("a_gate", true, Some("a-company"));
+
+    let mut user = StatsigUser::with_user_id("a-user".to_string());
+    user.custom_ids = Some(HashMap::from([(
+        "companyID".to_string(),
+        "a-company".to_string(),
+    )]));
+    let company_user = StatsigUser::with_custom_ids(HashMap::from([(
+        "companyID".to_string(),
+        "a-company".to_string(),
+    )]));
+    let other_user = StatsigUser::with_user_id("b-user".to_string());
+
+    assert_eq!(overrides.get_gate_override(&user, "a_gate"), Some(true));
+    assert_eq!(
+        overrides.get_gate_override(&company_user, "a_gate"),
+        Some(true)
+    );
+    assert_eq!(
+        overrides.get_gate_override(&other_user, "a_gate"),
+        Some(false)
+    );
+
+    // The user ID override wins over the custom ID one
+    overrides.override_config("a_config", json!({"id": "user"}), Some("a-user"));
+    overrides.override_config("a_config", json!({"id": "company"}), Some("a-company"));
+    assert_eq!(
+        overrides.get_config_override(&user, "a_config"),
+        Some(json!({"id": "user"}))
+    );
+    assert_eq!(
+        overrides.get_config_override(&company_user, "a_config"),
+        Some(json!({"id": "company"}))
+    );
+    assert_eq!(overrides.get_config_override(&other_user, "a_config"), None);
+
+    overrides.remove_gate_override("a_gate", Some("a-user"));
+    overrides.remove_gate_override("a_gate", Some("a-company"));
+    assert_eq!(overrides.get_gate_override(&user, "a_gate"), Some(false));
+
+    overrides.remove_gate_override("a_gate", None);
+    assert_eq!(overrides.get_gate_override(&user, "a_gate"), None);
+
+    overrides.override_layer("a_layer", json!({"a": 1}), Some("b-user"));
+    assert_eq!(overrides.get_layer_override(&user, "a_layer"), None);
+    overrides.remove_all_overrides();
+    assert_eq!(overrides.get_layer_override(&other_user, "a_layer"), None);
+    assert_eq!(overrides.get_config_override(&user, "a_config"), None);
+}
+
+#[tokio::test]
+async fn test_local_mode_serves_defaults_and_overrides() {
+    use crate::statsig::internal::StatsigDriver;
+    use crate::{EvaluationReason, StatsigOptions};
+
+    let options = StatsigOptions {
+        local_mode: true,
+        ..StatsigOptions::default()
+    };
+    let driver = StatsigDriver::new("secret key", options).unwrap();
+    driver.initialize().await;
+
+    let user = StatsigUser::with_user_id("a-user".to_string());
+    assert!(!driver.check_gate(&user, "a_gate"));
+
+    driver.override_gate("a_gate", true, Some("a-user"));
+    let gate = driver.get_feature_gate(&user, "a_gate");
+    assert!(gate.value);
+    assert_eq!(
+        gate.evaluation_details.reason,
+        EvaluationReason::LocalOverride
+    );
+
+    let other_user = StatsigUser::with_user_id("b-user".to_string());
+    assert!(!driver.check_gate(&other_user, "a_gate"));
+}
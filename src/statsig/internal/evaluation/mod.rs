@@ -1,15 +1,23 @@
+pub use compiled_condition::CompiledCondition;
+pub use eval_cache::EvalCache;
 pub use eval_result::EvalResult;
 pub use eval_details::EvalDetails;
 pub use eval_details::EvaluationReason;
 pub use statsig_evaluator::StatsigEvaluator;
 
+pub mod eval_cache;
 pub mod eval_details;
+pub mod id_list;
+pub mod local_overrides;
 pub mod specs;
 
 mod client_init_response_formatter;
+mod compiled_condition;
+#[cfg(feature = "geo-ip")]
 mod country_lookup;
 mod eval_helpers;
 mod eval_result;
 mod statsig_evaluator;
 mod statsig_user_eval_ext;
+#[cfg(feature = "ua-parser")]
 mod ua_parser;
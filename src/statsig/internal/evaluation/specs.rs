@@ -1,12 +1,18 @@
 use crate::statsig::internal::data_types::APISpec;
+use crate::statsig::internal::evaluation::eval_details::EvalDetails;
 use std::collections::HashMap;
 
+#[cfg(feature = "ua-parser")]
+use super::compiled_condition::ConditionType;
+
+#[derive(Clone)]
 pub struct Specs {
     pub last_sync_time: u64,
     pub gates: HashMap<String, APISpec>,
     pub configs: HashMap<String, APISpec>,
     pub layers: HashMap<String, APISpec>,
     pub experiment_to_layer: HashMap<String, String>,
+    pub eval_details: EvalDetails,
 }
 
 impl Specs {
@@ -17,14 +23,20 @@ impl Specs {
             configs: HashMap::new(),
             layers: HashMap::new(),
             experiment_to_layer: HashMap::new(),
+            eval_details: EvalDetails::default(),
         }
     }
 
-    pub fn update(&mut self, new_specs: Specs) {
-        self.last_sync_time = new_specs.last_sync_time;
-        self.gates = new_specs.gates;
-        self.configs = new_specs.configs;
-        self.layers = new_specs.layers;
-        self.experiment_to_layer = new_specs.experiment_to_layer;
+    #[cfg(feature = "ua-parser")]
+    pub fn has_ua_based_conditions(&self) -> bool {
+        let specs = self
+            .gates
+            .values()
+            .chain(self.configs.values())
+            .chain(self.layers.values());
+        specs
+            .flat_map(|spec| spec.rules.iter())
+            .flat_map(|rule| rule.conditions.iter())
+            .any(|condition| condition.compiled().condition_type == ConditionType::UaBased)
     }
 }
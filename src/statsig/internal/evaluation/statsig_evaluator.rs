@@ -1,30 +1,53 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::statsig::internal::evaluation::eval_helpers::{
-    compare_str_with_regex, compare_time, value_to_string,
+    compare_str_with_regex, compare_strings_in_set, compare_time, hash_name, value_to_string,
 };
 use serde_json::Value::Null;
 use serde_json::{json, Value};
+use tracing::instrument;
 
 use crate::statsig::internal::evaluation::client_init_response_formatter::ClientInitResponseFormatter;
-use crate::{unwrap_or_return, StatsigOptions, StatsigUser};
+use crate::statsig::statsig_evaluation_trace::{ConditionTrace, EvaluationTrace, RuleTrace};
+use crate::{
+    unwrap_or_return, GeoIpResolver, StatsigOptions, StatsigUser, StickyValues, UserAgentResolver,
+    UserPersistentStorage,
+};
 
 use super::super::data_types::{APICondition, APIRule, APISpec};
 use super::super::statsig_store::StatsigStore;
+use super::compiled_condition::{ConditionType, Operator};
+#[cfg(feature = "geo-ip")]
 use super::country_lookup::CountryLookup;
+use super::eval_cache::EvalCache;
 use super::eval_details::{EvalDetails, EvaluationReason};
 use super::eval_helpers::{
     compare_numbers, compare_strings_in_array, compare_versions, compute_user_hash,
 };
 use super::eval_result::EvalResult;
+use super::local_overrides::LocalOverrides;
+#[cfg(feature = "ua-parser")]
 use super::ua_parser::UserAgentParser;
 
+pub struct AllEvalResults {
+    pub gates: Vec<(String, EvalResult)>,
+    pub configs: Vec<(String, EvalResult)>,
+    pub experiments: Vec<(String, EvalResult)>,
+    pub layers: Vec<(String, EvalResult)>,
+}
+
 pub struct StatsigEvaluator {
     pub spec_store: Arc<StatsigStore>,
+    pub local_overrides: LocalOverrides,
 
+    geo_ip_resolver: Option<Arc<dyn GeoIpResolver>>,
+    user_agent_resolver: Option<Arc<dyn UserAgentResolver>>,
+    #[cfg(feature = "geo-ip")]
     country_lookup: CountryLookup,
+    #[cfg(feature = "ua-parser")]
     ua_parser: UserAgentParser,
 }
 
@@ -32,21 +55,177 @@ impl StatsigEvaluator {
     pub fn new(spec_store: Arc<StatsigStore>, options: &StatsigOptions) -> StatsigEvaluator {
         StatsigEvaluator {
             spec_store,
+            local_overrides: LocalOverrides::new(),
+            geo_ip_resolver: options.geo_ip_resolver.clone(),
+            user_agent_resolver: options.user_agent_resolver.clone(),
+            #[cfg(feature = "geo-ip")]
             country_lookup: CountryLookup::new(),
-            ua_parser: UserAgentParser::new(options.disable_user_agent_support),
+            #[cfg(feature = "ua-parser")]
+            ua_parser: UserAgentParser::new(options),
         }
     }
 
+    // Waits for the user agent parser, but only if the loaded specs have
+    // ua_based conditions. Nothing is loaded in local mode, so it never waits.
+    pub async fn initialize(&self) {
+        #[cfg(feature = "ua-parser")]
+        if self
+            .spec_store
+            .use_specs(|specs, _| specs.has_ua_based_conditions())
+        {
+            self.ua_parser.wait_until_loaded().await;
+        }
+    }
+
+    #[instrument(level = "debug", skip_all, fields(gate = gate_name))]
     pub fn check_gate(&self, user: &StatsigUser, gate_name: &str) -> EvalResult {
-        self.eval(user, gate_name, "gate")
+        self.check_gate_impl(user, gate_name, None, None)
     }
 
+    #[instrument(level = "debug", skip_all, fields(config = config_name))]
     pub fn get_config(&self, user: &StatsigUser, config_name: &str) -> EvalResult {
-        self.eval(user, config_name, "config")
+        self.get_config_impl(user, config_name, None, None)
+    }
+
+    // Like check_gate, but reuses and fills `cache`, including for nested
+    // gate checks. The cache must only ever be used with the same user.
+    #[instrument(level = "debug", skip_all, fields(gate = gate_name))]
+    pub fn check_gate_cached(
+        &self,
+        user: &StatsigUser,
+        gate_name: &str,
+        cache: &EvalCache,
+    ) -> EvalResult {
+        self.check_gate_impl(user, gate_name, Some(cache), None)
+    }
+
+    #[instrument(level = "debug", skip_all, fields(config = config_name))]
+    pub fn get_config_cached(
+        &self,
+        user: &StatsigUser,
+        config_name: &str,
+        cache: &EvalCache,
+    ) -> EvalResult {
+        self.get_config_impl(user, config_name, Some(cache), None)
+    }
+
+    // Returns the unit's saved group while the experiment is active, saving
+    // it on first assignment, and drops it once the experiment has ended.
+    #[instrument(level = "debug", skip_all, fields(experiment = experiment_name))]
+    pub fn get_experiment_persisted(
+        &self,
+        user: &StatsigUser,
+        experiment_name: &str,
+        storage: &dyn UserPersistentStorage,
+    ) -> EvalResult {
+        if let Some(value) = self
+            .local_overrides
+            .get_config_override(user, experiment_name)
+        {
+            return EvalResult::local_override(self.spec_store.get_eval_details(), false, value);
+        }
+
+        self.spec_store
+            .use_spec("config", experiment_name, |spec, eval_details| {
+                let (is_active, unit_id) = match spec {
+                    Some(spec) => (spec.is_active, user.get_unit_id(&spec.id_type)),
+                    None => (None, None),
+                };
+                let key = match (spec, unit_id) {
+                    (Some(spec), Some(unit_id)) if is_active.is_some() => {
+                        format!("{}:{}", unit_id, spec.id_type)
+                    }
+                    _ => return self.eval_spec(user, spec, eval_details),
+                };
+
+                let persisted = storage
+                    .load(&key)
+                    .and_then(|mut values| values.remove(experiment_name));
+                if is_active != Some(true) {
+                    if persisted.is_some() {
+                        storage.delete(&key, experiment_name);
+                    }
+                    return self.eval_spec(user, spec, eval_details);
+                }
+                if let Some(sticky) = persisted {
+                    return EvalResult::persisted(eval_details, sticky);
+                }
+
+                let result = self.eval_spec(user, spec, eval_details);
+                if result.is_experiment_group {
+                    storage.save(&key, experiment_name, StickyValues::new(&result));
+                }
+                result
+            })
     }
 
+    pub fn explain_gate(&self, user: &StatsigUser, gate_name: &str) -> EvaluationTrace {
+        let mut trace = EvaluationTrace::new(gate_name, "gate");
+        self.check_gate_impl(user, gate_name, None, Some(&mut trace));
+        trace
+    }
+
+    pub fn explain_config(&self, user: &StatsigUser, config_name: &str) -> EvaluationTrace {
+        let mut trace = EvaluationTrace::new(config_name, "config");
+        self.get_config_impl(user, config_name, None, Some(&mut trace));
+        trace
+    }
+
+    #[instrument(level = "debug", skip_all, fields(layer = layer_name))]
     pub fn get_layer(&self, user: &StatsigUser, layer_name: &str) -> EvalResult {
-        self.eval(user, layer_name, "layer")
+        if let Some(value) = self.local_overrides.get_layer_override(user, layer_name) {
+            return EvalResult::local_override(self.spec_store.get_eval_details(), false, value);
+        }
+        self.eval(user, layer_name, "layer", None, None)
+    }
+
+    // Evaluates every spec against one specs snapshot, instead of looking
+    // each one up by name.
+    #[instrument(level = "debug", skip_all)]
+    pub fn evaluate_all(&self, user: &StatsigUser) -> AllEvalResults {
+        self.spec_store.use_specs(|specs, eval_details| {
+            let mut results = AllEvalResults {
+                gates: vec![],
+                configs: vec![],
+                experiments: vec![],
+                layers: vec![],
+            };
+            let overrides = &self.local_overrides;
+
+            for (name, spec) in specs.gates.iter() {
+                if spec.entity == "segment" || spec.entity == "holdout" {
+                    continue;
+                }
+                let result = match overrides.get_gate_override(user, name) {
+                    Some(value) => {
+                        EvalResult::local_override(eval_details.clone(), value, json!(value))
+                    }
+                    None => self.eval_spec(user, Some(spec), eval_details.clone()),
+                };
+                results.gates.push((name.clone(), result));
+            }
+
+            for (name, spec) in specs.configs.iter() {
+                let result = match overrides.get_config_override(user, name) {
+                    Some(value) => EvalResult::local_override(eval_details.clone(), false, value),
+                    None => self.eval_spec(user, Some(spec), eval_details.clone()),
+                };
+                match spec.entity.as_str() {
+                    "experiment" => results.experiments.push((name.clone(), result)),
+                    _ => results.configs.push((name.clone(), result)),
+                }
+            }
+
+            for (name, spec) in specs.layers.iter() {
+                let result = match overrides.get_layer_override(user, name) {
+                    Some(value) => EvalResult::local_override(eval_details.clone(), false, value),
+                    None => self.eval_spec(user, Some(spec), eval_details.clone()),
+                };
+                results.layers.push((name.clone(), result));
+            }
+
+            results
+        })
     }
 
     pub fn get_client_initialize_response(&self, user: &StatsigUser) -> Value {
@@ -59,10 +238,84 @@ impl StatsigEvaluator {
         )
     }
 
-    fn eval(&self, user: &StatsigUser, spec_name: &str, spec_type: &str) -> EvalResult {
+    fn check_gate_impl(
+        &self,
+        user: &StatsigUser,
+        gate_name: &str,
+        cache: Option<&EvalCache>,
+        mut trace: Option<&mut EvaluationTrace>,
+    ) -> EvalResult {
+        // The cache is keyed by the sync time of the snapshot the result was
+        // evaluated against, so a sync in between can't mislabel it
+        self.spec_store.use_specs(|specs, eval_details| {
+            // Traces always evaluate from scratch so every step is recorded
+            let sync_time = specs.last_sync_time;
+            if let (Some(cache), None) = (cache, &trace) {
+                if let Some(result) = cache.get_gate(gate_name, sync_time) {
+                    return result;
+                }
+            }
+
+            let result = match self.local_overrides.get_gate_override(user, gate_name) {
+                Some(value) => EvalResult::local_override(eval_details, value, json!(value)),
+                None => {
+                    let spec = specs.gates.get(gate_name);
+                    self.eval_spec_impl(user, spec, eval_details, cache, trace.as_deref_mut())
+                }
+            };
+            if let Some(cache) = cache {
+                cache.set_gate(gate_name, sync_time, &result);
+            }
+            if let Some(trace) = trace {
+                trace.set_result(&result);
+            }
+            result
+        })
+    }
+
+    fn get_config_impl(
+        &self,
+        user: &StatsigUser,
+        config_name: &str,
+        cache: Option<&EvalCache>,
+        mut trace: Option<&mut EvaluationTrace>,
+    ) -> EvalResult {
+        self.spec_store.use_specs(|specs, eval_details| {
+            let sync_time = specs.last_sync_time;
+            if let (Some(cache), None) = (cache, &trace) {
+                if let Some(result) = cache.get_config(config_name, sync_time) {
+                    return result;
+                }
+            }
+
+            let result = match self.local_overrides.get_config_override(user, config_name) {
+                Some(value) => EvalResult::local_override(eval_details, false, value),
+                None => {
+                    let spec = specs.configs.get(config_name);
+                    self.eval_spec_impl(user, spec, eval_details, cache, trace.as_deref_mut())
+                }
+            };
+            if let Some(cache) = cache {
+                cache.set_config(config_name, sync_time, &result);
+            }
+            if let Some(trace) = trace {
+                trace.set_result(&result);
+            }
+            result
+        })
+    }
+
+    fn eval(
+        &self,
+        user: &StatsigUser,
+        spec_name: &str,
+        spec_type: &str,
+        cache: Option<&EvalCache>,
+        trace: Option<&mut EvaluationTrace>,
+    ) -> EvalResult {
         self.spec_store
             .use_spec(spec_type, spec_name, |spec, eval_details| {
-                self.eval_spec(user, spec, eval_details)
+                self.eval_spec_impl(user, spec, eval_details, cache, trace)
             })
     }
 
@@ -71,11 +324,25 @@ impl StatsigEvaluator {
         user: &StatsigUser,
         spec: Option<&APISpec>,
         eval_details: EvalDetails,
+    ) -> EvalResult {
+        self.eval_spec_impl(user, spec, eval_details, None, None)
+    }
+
+    // Records each rule checked into `trace` when one is given
+    fn eval_spec_impl(
+        &self,
+        user: &StatsigUser,
+        spec: Option<&APISpec>,
+        eval_details: EvalDetails,
+        cache: Option<&EvalCache>,
+        mut trace: Option<&mut EvaluationTrace>,
     ) -> EvalResult {
         let spec: &APISpec = match spec {
             Some(spec) => spec,
             None =>  {
-                if eval_details.reason != EvaluationReason::Uninitialized {
+                if eval_details.reason != EvaluationReason::Uninitialized
+                    && eval_details.reason != EvaluationReason::LocalMode
+                {
                     return EvalResult::unrecognized(eval_details)
                 } else {
                     return EvalResult::uninitialized(eval_details)
@@ -83,6 +350,11 @@ impl StatsigEvaluator {
             }
         };
 
+        if let Some(trace) = trace.as_deref_mut() {
+            trace.found = true;
+            trace.enabled = spec.enabled;
+        }
+
         if !spec.enabled {
             return EvalResult {
                 json_value: Some(spec.default_value.clone()),
@@ -96,11 +368,16 @@ impl StatsigEvaluator {
         let mut exposures: Vec<HashMap<String, String>> = vec![];
         let cloned_eval_detail = eval_details.clone();
         for rule in spec.rules.iter() {
-            let mut result = self.eval_rule(user, rule);
+            let mut rule_trace = trace.as_ref().map(|_| RuleTrace::new(rule));
+            let mut result = self.eval_rule(user, rule, cache, rule_trace.as_mut());
+            if let Some(rule_trace) = rule_trace.as_mut() {
+                rule_trace.conditions_passed = result.bool_value && !result.unsupported;
+            }
 
             if result.unsupported {
+                Self::push_rule_trace(&mut trace, rule_trace);
                 let mut override_details: EvalDetails = self.spec_store.get_eval_details();
-                override_details.reason = EvaluationReason::Unsupported;
+                override_details.reason = result.evaluation_details.reason;
                 result.evaluation_details = override_details;
                 result.config_version = spec.version;
                 return result;
@@ -111,14 +388,27 @@ impl StatsigEvaluator {
             }
 
             if !result.bool_value {
+                Self::push_rule_trace(&mut trace, rule_trace);
                 continue;
             }
 
-            if let Some(delegated_result) = self.eval_delegate(user, rule, &exposures) {
+            let delegate_trace = rule_trace.as_mut().map(|t| &mut t.delegate);
+            if let Some(delegated_result) =
+                self.eval_delegate(user, rule, &exposures, cache, delegate_trace)
+            {
+                if let Some(rule_trace) = rule_trace.as_mut() {
+                    rule_trace.passed = delegated_result.bool_value;
+                }
+                Self::push_rule_trace(&mut trace, rule_trace);
                 return delegated_result;
             }
 
             let pass = self.eval_pass_percentage(user, rule, &spec.salt);
+            if let Some(rule_trace) = rule_trace.as_mut() {
+                rule_trace.bucket = self.get_pass_bucket(user, rule, &spec.salt);
+                rule_trace.passed = pass;
+            }
+            Self::push_rule_trace(&mut trace, rule_trace);
             return EvalResult {
                 bool_value: pass,
                 json_value: match pass {
@@ -147,12 +437,33 @@ impl StatsigEvaluator {
         }
     }
 
-    fn eval_rule(&self, user: &StatsigUser, rule: &APIRule) -> EvalResult {
+    fn push_rule_trace(trace: &mut Option<&mut EvaluationTrace>, rule_trace: Option<RuleTrace>) {
+        if let (Some(trace), Some(rule_trace)) = (trace, rule_trace) {
+            trace.rules.push(rule_trace);
+        }
+    }
+
+    fn eval_rule(
+        &self,
+        user: &StatsigUser,
+        rule: &APIRule,
+        cache: Option<&EvalCache>,
+        mut trace: Option<&mut RuleTrace>,
+    ) -> EvalResult {
         let mut exposures: Vec<HashMap<String, String>> = vec![];
         let mut pass = true;
 
         for condition in rule.conditions.iter() {
-            let result = self.eval_condition(user, condition);
+            let mut condition_trace = trace.as_ref().map(|_| ConditionTrace::new(condition));
+            let result = self.eval_condition(user, condition, cache, condition_trace.as_mut());
+            if let (Some(trace), Some(mut condition_trace)) =
+                (trace.as_deref_mut(), condition_trace)
+            {
+                condition_trace.passed = result.bool_value;
+                condition_trace.unsupported = result.unsupported;
+                trace.conditions.push(condition_trace);
+            }
+
             if result.unsupported {
                 return result;
             }
@@ -181,11 +492,21 @@ impl StatsigEvaluator {
         user: &StatsigUser,
         rule: &APIRule,
         exposures: &[HashMap<String, String>],
+        cache: Option<&EvalCache>,
+        trace: Option<&mut Option<Box<EvaluationTrace>>>,
     ) -> Option<EvalResult> {
         let delegate = unwrap_or_return!(&rule.config_delegate, None);
         self.spec_store
             .use_spec("config", delegate, |spec, eval_details| {
-                let mut result = self.eval_spec(user, spec, eval_details);
+                let mut delegate_trace = trace
+                    .as_ref()
+                    .map(|_| EvaluationTrace::new(delegate, "config"));
+                let mut result =
+                    self.eval_spec_impl(user, spec, eval_details, cache, delegate_trace.as_mut());
+                if let (Some(trace), Some(mut delegate_trace)) = (trace, delegate_trace) {
+                    delegate_trace.set_result(&result);
+                    *trace = Some(Box::new(delegate_trace));
+                }
                 if result.unsupported {
                     return Some(result);
                 }
@@ -206,84 +527,166 @@ impl StatsigEvaluator {
             })
     }
 
-    fn eval_condition(&self, user: &StatsigUser, condition: &APICondition) -> EvalResult {
-        let target_value = json!(condition.target_value);
-        let condition_type = condition.condition_type.to_lowercase();
+    // Errors with the reason to report when the value can't be resolved,
+    // e.g. because the geo-ip feature is disabled.
+    fn get_value_from_ip(
+        &self,
+        user: &StatsigUser,
+        field: &Option<String>,
+    ) -> Result<Value, EvaluationReason> {
+        if let (Some(resolver), Some(field)) = (&self.geo_ip_resolver, field) {
+            if let Some(value) = resolver.resolve(user, field) {
+                return Ok(value);
+            }
+        }
+
+        #[cfg(feature = "geo-ip")]
+        {
+            Ok(self.country_lookup.get_value_from_ip(user, field))
+        }
+        #[cfg(not(feature = "geo-ip"))]
+        {
+            Err(EvaluationReason::Unsupported)
+        }
+    }
+
+    fn get_value_from_user_agent(
+        &self,
+        user: &StatsigUser,
+        field: &Option<String>,
+    ) -> Result<Value, EvaluationReason> {
+        if let (Some(resolver), Some(field)) = (&self.user_agent_resolver, field) {
+            if let Some(value) = resolver.resolve(user, field) {
+                return Ok(value);
+            }
+        }
+
+        #[cfg(feature = "ua-parser")]
+        {
+            self.ua_parser
+                .get_value_from_user_agent(user, field)
+                .ok_or(EvaluationReason::UserAgentParserNotReady)
+        }
+        #[cfg(not(feature = "ua-parser"))]
+        {
+            Err(EvaluationReason::Unsupported)
+        }
+    }
 
-        let value = match condition_type.as_str() {
-            "public" => return EvalResult::boolean(true),
-            "fail_gate" | "pass_gate" => {
-                return self.eval_nested_gate(user, &target_value, &condition_type);
+    fn eval_condition(
+        &self,
+        user: &StatsigUser,
+        condition: &APICondition,
+        cache: Option<&EvalCache>,
+        trace: Option<&mut ConditionTrace>,
+    ) -> EvalResult {
+        let compiled = condition.compiled();
+
+        let value = match compiled.condition_type {
+            ConditionType::Public => return EvalResult::boolean(true),
+            ConditionType::FailGate | ConditionType::PassGate => {
+                return self.eval_nested_gate(
+                    user,
+                    &compiled.target_str,
+                    compiled.condition_type,
+                    cache,
+                    trace,
+                );
             }
-            "ip_based" => match user.get_user_value(&condition.field) {
-                Null => self
-                    .country_lookup
-                    .get_value_from_ip(user, &condition.field),
+            ConditionType::IpBased => match user.get_user_value(&condition.field) {
+                v if v.is_null() => match self.get_value_from_ip(user, &condition.field) {
+                    Ok(v) => Cow::Owned(v),
+                    Err(reason) => return EvalResult::unsupported_with_reason(reason),
+                },
                 v => v,
             },
-            "ua_based" => match user.get_user_value(&condition.field) {
-                Null => self
-                    .ua_parser
-                    .get_value_from_user_agent(user, &condition.field),
+            ConditionType::UaBased => match user.get_user_value(&condition.field) {
+                v if v.is_null() => match self.get_value_from_user_agent(user, &condition.field) {
+                    Ok(v) => Cow::Owned(v),
+                    Err(reason) => return EvalResult::unsupported_with_reason(reason),
+                },
                 v => v,
             },
-            "user_field" => user.get_user_value(&condition.field),
-            "environment_field" => user.get_value_from_environment(&condition.field),
-            "current_time" => match SystemTime::now().duration_since(UNIX_EPOCH).ok() {
-                Some(time) => json!(time.as_millis().to_string()),
-                _ => Null,
+            ConditionType::UserField => user.get_user_value(&condition.field),
+            ConditionType::EnvironmentField => {
+                Cow::Owned(user.get_value_from_environment(&condition.field))
+            }
+            ConditionType::CurrentTime => match SystemTime::now().duration_since(UNIX_EPOCH).ok() {
+                Some(time) => Cow::Owned(json!(time.as_millis().to_string())),
+                _ => Cow::Borrowed(&Null),
+            },
+            ConditionType::UserBucket => match self.get_hash_for_user_bucket(user, condition) {
+                Some(hash) => Cow::Owned(json!(hash)),
+                _ => Cow::Borrowed(&Null),
             },
-            "user_bucket" => match self.get_hash_for_user_bucket(user, condition) {
-                Some(hash) => json!(hash),
-                _ => Null,
+            ConditionType::UnitId => match user.get_unit_id(&condition.id_type) {
+                Some(unit_id) => Cow::Owned(Value::String(unit_id.to_string())),
+                None => Cow::Borrowed(&Null),
             },
-            "unit_id" => json!(user.get_unit_id(&condition.id_type)),
-            _ => return EvalResult::unsupported(),
+            ConditionType::Unknown => return EvalResult::unsupported(),
         };
 
-        let operator = match &condition.operator {
-            Some(operator) => operator.as_str(),
+        if let Some(trace) = trace {
+            trace.user_value = value.clone().into_owned();
+        }
+
+        let operator = match compiled.operator {
+            Some(operator) => operator,
             None => return EvalResult::unsupported(),
         };
 
         let result = match operator {
             // numerical comparison
-            "gt" | "gte" | "lt" | "lte" => {
-                compare_numbers(&value, &target_value, operator).unwrap_or(false)
+            Operator::Gt | Operator::Gte | Operator::Lt | Operator::Lte => {
+                compare_numbers(&value, compiled.target_number, operator).unwrap_or(false)
             }
 
             // version comparison
-            "version_gt" | "version_gte" | "version_lt" | "version_lte" | "version_eq"
-            | "version_neq" => compare_versions(&value, &target_value, operator).unwrap_or(false),
+            Operator::VersionGt
+            | Operator::VersionGte
+            | Operator::VersionLt
+            | Operator::VersionLte
+            | Operator::VersionEq
+            | Operator::VersionNeq => {
+                compare_versions(&value, &compiled.target_version, operator).unwrap_or(false)
+            }
 
             // string/array comparison
-            "any"
-            | "none"
-            | "str_starts_with_any"
-            | "str_ends_with_any"
-            | "str_contains_any"
-            | "str_contains_none" => {
-                compare_strings_in_array(&value, &target_value, operator, true)
+            Operator::Any
+            | Operator::None
+            | Operator::AnyCaseSensitive
+            | Operator::NoneCaseSensitive => {
+                compare_strings_in_set(&value, compiled.target_set.as_ref(), operator)
             }
-            "any_case_sensitive" | "none_case_sensitive" => {
-                compare_strings_in_array(&value, &target_value, operator, false)
+            Operator::StrStartsWithAny
+            | Operator::StrEndsWithAny
+            | Operator::StrContainsAny
+            | Operator::StrContainsNone => {
+                compare_strings_in_array(&value, compiled.target_strings.as_deref(), operator)
             }
-            "str_matches" => compare_str_with_regex(&value, &target_value),
+            Operator::StrMatches => compare_str_with_regex(&value, compiled.regex.as_ref()),
 
             // time comparison
-            "before" | "after" | "on" => {
-                compare_time(&value, &target_value, operator).unwrap_or(false)
+            Operator::Before | Operator::After | Operator::On => {
+                compare_time(&value, compiled.target_time, operator).unwrap_or(false)
             }
 
-            "eq" => value == target_value,
-            "neq" => value != target_value,
+            Operator::InSegmentList | Operator::NotInSegmentList => {
+                let in_list = self.is_in_id_list(&value, &compiled.target_str);
+                if operator == Operator::InSegmentList {
+                    in_list
+                } else {
+                    !in_list
+                }
+            }
 
-            _ => return EvalResult::unsupported(),
+            Operator::Eq => *value == compiled.target_value,
+            Operator::Neq => *value != compiled.target_value,
         };
         EvalResult::boolean(result)
     }
 
-    fn eval_pass_percentage(&self, user: &StatsigUser, rule: &APIRule, spec_salt: &String) -> bool {
+    fn eval_pass_percentage(&self, user: &StatsigUser, rule: &APIRule, spec_salt: &str) -> bool {
         if rule.pass_percentage == 100f64 {
             return true;
         }
@@ -291,22 +694,35 @@ impl StatsigEvaluator {
             return false;
         }
         
-        let rule_salt = rule.salt.as_ref().unwrap_or(&rule.id);
-        let unit_id = user.get_unit_id(&rule.id_type).unwrap_or("".to_string());
-        match compute_user_hash(format!("{}.{}.{}", spec_salt, rule_salt, unit_id)) {
-            Some(hash) => ((hash % 10000) as f64) < rule.pass_percentage * 100.0,
+        match self.get_pass_bucket(user, rule, spec_salt) {
+            Some(bucket) => (bucket as f64) < rule.pass_percentage * 100.0,
             None => false,
         }
     }
 
+    fn get_pass_bucket(&self, user: &StatsigUser, rule: &APIRule, spec_salt: &str) -> Option<u64> {
+        let rule_salt = rule.salt.as_ref().unwrap_or(&rule.id);
+        let unit_id = user.get_unit_id(&rule.id_type).unwrap_or_default();
+        let hash = compute_user_hash(&[spec_salt, rule_salt, unit_id])?;
+        Some((hash % 10000) as u64)
+    }
+
     fn eval_nested_gate(
         &self,
         user: &StatsigUser,
-        target_value: &Value,
-        condition_type: &String,
+        gate_name: &str,
+        condition_type: ConditionType,
+        cache: Option<&EvalCache>,
+        trace: Option<&mut ConditionTrace>,
     ) -> EvalResult {
-        let gate_name = value_to_string(target_value).expect("eval_nested_gate");
-        let result = self.check_gate(user, &gate_name);
+        let mut gate_trace = trace
+            .as_ref()
+            .map(|_| EvaluationTrace::new(gate_name, "gate"));
+        let result = self.check_gate_impl(user, gate_name, cache, gate_trace.as_mut());
+        if let (Some(trace), Some(gate_trace)) = (trace, gate_trace) {
+            trace.user_value = json!(result.bool_value);
+            trace.nested_gate = Some(Box::new(gate_trace));
+        }
 
         if result.unsupported {
             return result;
@@ -314,19 +730,16 @@ impl StatsigEvaluator {
 
         let mut gate_value = result.bool_value;
         let exposure = HashMap::from([
-            ("gate".to_string(), gate_name),
+            ("gate".to_string(), gate_name.to_string()),
             ("gateValue".to_string(), gate_value.to_string()),
             ("ruleID".to_string(), result.rule_id),
         ]);
 
-        if condition_type == "fail_gate" {
+        if condition_type == ConditionType::FailGate {
             gate_value = !gate_value;
         }
 
-        let mut exposures = match result.secondary_exposures {
-            Some(v) => v,
-            None => vec![],
-        };
+        let mut exposures = result.secondary_exposures.unwrap_or_default();
 
         exposures.push(exposure);
 
@@ -336,22 +749,74 @@ impl StatsigEvaluator {
         }
     }
 
+    fn is_in_id_list(&self, value: &Value, list_name: &str) -> bool {
+        if value.is_null() {
+            return false;
+        }
+
+        let id = unwrap_or_return!(value_to_string(value), false);
+        let hashed_id = hash_name(&id);
+        self.spec_store.id_list_contains(list_name, &hashed_id[..8])
+    }
+
     fn get_hash_for_user_bucket(
         &self,
         user: &StatsigUser,
         condition: &APICondition,
     ) -> Option<usize> {
-        let unit_id = user
-            .get_unit_id(&condition.id_type)
-            .unwrap_or("".to_string());
-        let mut salt = "".to_string();
-        if let Some(add_values) = &condition.additional_values {
-            if let Value::String(s) = &add_values["salt"] {
-                salt = s.clone();
-            }
-        }
-
-        let hash = compute_user_hash(format!("{}.{}", salt, unit_id))?;
+        let unit_id = user.get_unit_id(&condition.id_type).unwrap_or_default();
+        let salt = &condition.compiled().user_bucket_salt;
+        let hash = compute_user_hash(&[salt, unit_id])?;
         Some(hash % 1000)
     }
 }
+
+#[cfg(not(all(feature = "geo-ip", feature = "ua-parser")))]
+#[tokio::test]
+async fn test_compiled_out_conditions_are_unsupported() {
+    use crate::statsig::internal::test_utils::{make_gate_spec, make_specs, serve_specs};
+    use crate::statsig::internal::StatsigDriver;
+
+    let gates = vec![
+        make_gate_spec(
+            "ip_gate",
+            json!([{
+                "type": "ip_based",
+                "operator": "any",
+                "field": "country",
+                "targetValue": ["US"],
+                "idType": "userID",
+            }]),
+        ),
+        make_gate_spec(
+            "ua_gate",
+            json!([{
+                "type": "ua_based",
+                "operator": "any",
+                "field": "os_name",
+                "targetValue": ["iOS"],
+                "idType": "userID",
+            }]),
+        ),
+    ];
+    let (options, _) = serve_specs(make_specs(gates, vec![], vec![], 1));
+    let driver = StatsigDriver::new("secret key", options).unwrap();
+    driver.initialize().await;
+
+    let mut user = StatsigUser::with_user_id("a-user".to_string());
+    user.ip = Some("1.1.1.1".to_string());
+    user.user_agent = Some("Mozilla/5.0 (iPhone; CPU iPhone OS 16_5 like Mac OS X)".to_string());
+
+    #[cfg(not(feature = "geo-ip"))]
+    {
+        let gate = driver.get_feature_gate(&user, "ip_gate");
+        assert!(!gate.value);
+        assert_eq!(gate.evaluation_details.reason, EvaluationReason::Unsupported);
+    }
+    #[cfg(not(feature = "ua-parser"))]
+    {
+        let gate = driver.get_feature_gate(&user, "ua_gate");
+        assert!(!gate.value);
+        assert_eq!(gate.evaluation_details.reason, EvaluationReason::Unsupported);
+    }
+}
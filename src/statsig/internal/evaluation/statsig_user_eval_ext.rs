@@ -1,71 +1,71 @@
+use std::borrow::Cow;
+
 use crate::StatsigUser;
 use serde_json::Value::Null;
 use serde_json::{json, Value};
 
 impl StatsigUser {
-    pub fn get_unit_id(&self, id_type: &String) -> Option<String> {
-        if id_type.to_lowercase() == *"userid" {
-            return self.user_id.clone();
+    pub fn get_unit_id(&self, id_type: &str) -> Option<&str> {
+        if id_type.eq_ignore_ascii_case("userid") {
+            return self.user_id.as_deref();
         }
 
-        let custom_ids = match &self.custom_ids {
-            Some(x) => x,
-            None => return None,
-        };
-
+        let custom_ids = self.custom_ids.as_ref()?;
         if let Some(custom_id) = custom_ids.get(id_type) {
-            return Some(custom_id.clone());
+            return Some(custom_id);
         }
 
-        if let Some(custom_id) = custom_ids.get(id_type.to_lowercase().as_str()) {
-            return Some(custom_id.clone());
-        }
-
-        None
+        // Only allocates when the exact id type is missing
+        custom_ids
+            .get(id_type.to_lowercase().as_str())
+            .map(String::as_str)
     }
 
-    pub fn get_user_value(&self, field: &Option<String>) -> Value {
+    // Borrows custom and private values. The built-in fields are Strings, so
+    // those are the only ones copied into a Value.
+    pub fn get_user_value(&self, field: &Option<String>) -> Cow<'_, Value> {
         let field = match field {
-            Some(f) => f,
-            _ => return Null,
+            Some(f) => f.as_str(),
+            _ => return Cow::Borrowed(&Null),
         };
 
-        let str_value = match field.to_lowercase().as_str() {
-            "userid" | "user_id" => &self.user_id,
-            "email" => &self.email,
-            "ip" => &self.ip,
-            "useragent" | "user_agent" => &self.user_agent,
-            "country" => &self.country,
-            "locale" => &self.locale,
-            "appversion" | "app_version" => &self.app_version,
-            _ => &None,
-        };
-
-        if let Some(value) = str_value {
-            return json!(value);
+        if let Some(value) = self.get_builtin_field(field) {
+            return Cow::Owned(Value::String(value.to_string()));
         }
 
-        if let Some(custom) = &self.custom {
-            if let Some(custom_value) = custom.get(field.as_str()) {
-                return custom_value.clone();
+        let maps = [&self.custom, &self.private_attributes];
+        let mut lowered = None;
+        for map in maps.into_iter().flatten() {
+            if let Some(value) = map.get(field) {
+                return Cow::Borrowed(value);
             }
-            if let Some(custom_value) = custom.get(field.to_uppercase().to_lowercase().as_str()) {
-                return custom_value.clone();
+            // Only allocates when the exact field is missing
+            let lowered = lowered.get_or_insert_with(|| field.to_lowercase());
+            if let Some(value) = map.get(lowered.as_str()) {
+                return Cow::Borrowed(value);
             }
         }
 
-        if let Some(private_attributes) = &self.private_attributes {
-            if let Some(private_value) = private_attributes.get(field.as_str()) {
-                return private_value.clone();
-            }
-            if let Some(private_value) =
-                private_attributes.get(field.to_uppercase().to_lowercase().as_str())
-            {
-                return private_value.clone();
-            }
-        }
+        Cow::Borrowed(&Null)
+    }
 
-        Null
+    fn get_builtin_field(&self, field: &str) -> Option<&str> {
+        let fields = [
+            ("userid", &self.user_id),
+            ("user_id", &self.user_id),
+            ("email", &self.email),
+            ("ip", &self.ip),
+            ("useragent", &self.user_agent),
+            ("user_agent", &self.user_agent),
+            ("country", &self.country),
+            ("locale", &self.locale),
+            ("appversion", &self.app_version),
+            ("app_version", &self.app_version),
+        ];
+        let (_, value) = fields
+            .into_iter()
+            .find(|(name, _)| field.eq_ignore_ascii_case(name))?;
+        value.as_deref()
     }
 
     pub fn get_value_from_environment(&self, field: &Option<String>) -> Value {
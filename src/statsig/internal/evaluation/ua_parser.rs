@@ -1,46 +1,92 @@
 use std::borrow::Cow;
-use std::sync::{Arc, RwLock};
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::{Arc, Once, OnceLock};
 
+use lazy_static::lazy_static;
 use serde_json::Value::Null;
 use serde_json::{json, Value};
+use tracing::error;
 use uaparser::{Parser, UserAgentParser as ExtUserAgentParser};
 
-use crate::{unwrap_or_return, StatsigUser};
+use crate::{unwrap_or_return, StatsigOptions, StatsigUser};
+
+struct ParserCell {
+    // None until loaded, Some(None) if loading failed
+    parser: OnceLock<Option<ExtUserAgentParser>>,
+    // Guards the background load, so it is only started once per cell
+    load_started: Once,
+}
+
+impl ParserCell {
+    fn new() -> Self {
+        ParserCell {
+            parser: OnceLock::new(),
+            load_started: Once::new(),
+        }
+    }
+}
+
+lazy_static! {
+    // Compiling the bundled regexes is slow, so it is shared by every client
+    static ref BUNDLED_PARSER: Arc<ParserCell> = Arc::new(ParserCell::new());
+}
 
 pub struct UserAgentParser {
-    parser: Arc<RwLock<Option<ExtUserAgentParser>>>,
+    cell: Arc<ParserCell>,
+    regex_path: Option<PathBuf>,
+    disabled: bool,
 }
 
 impl UserAgentParser {
-    pub fn new(disabled: bool) -> Self {
-        let mut inst = Self {
-            parser: Arc::from(RwLock::from(None)),
+    pub fn new(options: &StatsigOptions) -> Self {
+        let inst = Self {
+            cell: match options.user_agent_regex_path {
+                Some(_) => Arc::new(ParserCell::new()),
+                None => BUNDLED_PARSER.clone(),
+            },
+            regex_path: options.user_agent_regex_path.clone(),
+            disabled: options.disable_user_agent_support,
         };
 
-        if !disabled {
-            inst.load_parser();
+        if !inst.disabled {
+            let cell = inst.cell.clone();
+            let regex_path = inst.regex_path.clone();
+            inst.cell.load_started.call_once(move || {
+                std::thread::spawn(move || Self::load_parser(&cell.parser, &regex_path));
+            });
         }
 
         inst
     }
 
-    pub fn get_value_from_user_agent(&self, user: &StatsigUser, field: &Option<String>) -> Value {
-        let field_lowered = match field {
-            Some(f) => f.to_lowercase(),
-            _ => return Null,
-        };
+    pub async fn wait_until_loaded(&self) {
+        if self.disabled || self.cell.parser.get().is_some() {
+            return;
+        }
 
-        let user_agent = match &user.user_agent {
-            Some(ua) => ua,
-            _ => return Null,
-        };
+        let cell = self.cell.clone();
+        let regex_path = self.regex_path.clone();
+        let _ =
+            tokio::task::spawn_blocking(move || Self::load_parser(&cell.parser, &regex_path)).await;
+    }
 
-        if user_agent.len() > 1000 {
-            return Null;
+    // Returns None if the parser is needed but has not finished loading.
+    pub fn get_value_from_user_agent(
+        &self,
+        user: &StatsigUser,
+        field: &Option<String>,
+    ) -> Option<Value> {
+        let field = unwrap_or_return!(field, Some(Null));
+        let user_agent = unwrap_or_return!(&user.user_agent, Some(Null));
+        if user_agent.len() > 1000 || self.disabled {
+            return Some(Null);
         }
 
-        let lock = unwrap_or_return!(self.parser.read().ok(), Null);
-        let parser = unwrap_or_return!(&*lock, Null);
+        let parser = match self.cell.parser.get()? {
+            Some(parser) => parser,
+            None => return Some(Null),
+        };
 
         fn get_json_version(
             major: Option<Cow<str>>,
@@ -57,7 +103,7 @@ impl UserAgentParser {
         }
 
         let parsed = parser.parse(user_agent);
-        match field_lowered.as_str() {
+        let value = match field.to_lowercase().as_str() {
             "os_name" | "osname" => json!(parsed.os.family),
             "os_version" | "osversion" => {
                 let os = parsed.os;
@@ -68,21 +114,61 @@ impl UserAgentParser {
                 let ua = parsed.user_agent;
                 get_json_version(ua.major, ua.minor, ua.patch)
             }
+            "device_family" | "devicefamily" => json!(parsed.device.family),
+            "device_model" | "devicemodel" => json!(parsed.device.model),
             _ => Null,
-        }
+        };
+        Some(value)
     }
 
-    fn load_parser(&mut self) {
-        let parser = self.parser.clone();
-        std::thread::spawn(move || {
-            if let Ok(mut lock) = parser.write() {
-                *lock = Some(
-                    ExtUserAgentParser::from_bytes(include_bytes!(
-                        "resources/ua_parser_regex.yaml"
-                    ))
-                    .expect("ua_parser"),
-                );
+    fn load_parser(parser: &OnceLock<Option<ExtUserAgentParser>>, regex_path: &Option<PathBuf>) {
+        parser.get_or_init(|| {
+            let result = match regex_path {
+                Some(path) => File::open(path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|file| {
+                        ExtUserAgentParser::from_file(file).map_err(|e| e.to_string())
+                    }),
+                None => {
+                    ExtUserAgentParser::from_bytes(include_bytes!("resources/ua_parser_regex.yaml"))
+                        .map_err(|e| e.to_string())
+                }
+            };
+
+            match result {
+                Ok(parser) => Some(parser),
+                Err(e) => {
+                    error!("Failed to load user agent parser: {}", e);
+                    None
+                }
             }
         });
     }
 }
+
+#[test]
+fn test_user_agent_fields() {
+    let user = StatsigUser {
+        user_agent: Some(
+            "Mozilla/5.0 (iPhone; CPU iPhone OS 16_5 like Mac OS X) AppleWebKit/605.1.15 \
+             (KHTML, like Gecko) Version/16.5 Mobile/15E148 Safari/604.1"
+                .to_string(),
+        ),
+        ..StatsigUser::with_user_id("a-user".to_string())
+    };
+    let field = |name: &str| Some(name.to_string());
+
+    // Until the regexes are compiled the value can't be known
+    let parser = UserAgentParser {
+        cell: Arc::new(ParserCell::new()),
+        regex_path: None,
+        disabled: false,
+    };
+    let get = |name: &str| parser.get_value_from_user_agent(&user, &field(name));
+    assert_eq!(get("os_name"), None);
+
+    UserAgentParser::load_parser(&parser.cell.parser, &None);
+    assert_eq!(get("os_name"), Some(json!("iOS")));
+    assert_eq!(get("device_family"), Some(json!("iPhone")));
+    assert_eq!(get("device_model"), Some(json!("iPhone")));
+}
@@ -1,7 +1,9 @@
+#[cfg(feature = "geo-ip")]
 pub trait UsizeExt {
     fn post_inc(&mut self) -> Self;
 }
 
+#[cfg(feature = "geo-ip")]
 impl UsizeExt for usize {
     fn post_inc(&mut self) -> Self {
         let was = *self;
@@ -1,8 +1,9 @@
-use crate::statsig::internal::EvalResult;
-use crate::{Statsig, StatsigUser};
+use crate::statsig::internal::{EvalResult, StatsigDriver};
+use crate::StatsigUser;
 use serde::de::DeserializeOwned;
 use serde_json::{from_value, Value};
 use std::collections::HashMap;
+use std::sync::Weak;
 
 use super::evaluation::eval_details::EvalDetails;
 
@@ -22,15 +23,149 @@ impl Layer {
         }
 
         if let Ok(value) = from_value(self.value[key].clone()) {
-            Statsig::log_layer_parameter_exposure(self, key, &self.log_data);
+            if self.log_data.disable_exposure_logging {
+                return value;
+            }
+            if let Some(driver) = self.log_data.driver.upgrade() {
+                driver.log_layer_parameter_exposure(self, key, &self.log_data);
+            }
             return value;
         }
 
         default
     }
+
+    // Returns the raw parameter value, logging an exposure if it exists
+    pub fn get_value(&self, key: &str) -> Option<Value> {
+        self.log_parameter(key, self.get_value_with_exposure_logging_disabled(key))
+    }
+
+    pub fn get_value_with_exposure_logging_disabled(&self, key: &str) -> Option<Value> {
+        self.value.get(key).cloned()
+    }
+
+    // The typed getters return None if the parameter is missing or has a
+    // different type, and only log an exposure when they return a value.
+    pub fn get_string(&self, key: &str) -> Option<String> {
+        self.log_parameter(key, self.get_string_with_exposure_logging_disabled(key))
+    }
+
+    pub fn get_string_with_exposure_logging_disabled(&self, key: &str) -> Option<String> {
+        self.value.get(key)?.as_str().map(str::to_string)
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        self.log_parameter(key, self.get_bool_with_exposure_logging_disabled(key))
+    }
+
+    pub fn get_bool_with_exposure_logging_disabled(&self, key: &str) -> Option<bool> {
+        self.value.get(key)?.as_bool()
+    }
+
+    pub fn get_f64(&self, key: &str) -> Option<f64> {
+        self.log_parameter(key, self.get_f64_with_exposure_logging_disabled(key))
+    }
+
+    pub fn get_f64_with_exposure_logging_disabled(&self, key: &str) -> Option<f64> {
+        self.value.get(key)?.as_f64()
+    }
+
+    pub fn get_i64(&self, key: &str) -> Option<i64> {
+        self.log_parameter(key, self.get_i64_with_exposure_logging_disabled(key))
+    }
+
+    pub fn get_i64_with_exposure_logging_disabled(&self, key: &str) -> Option<i64> {
+        self.value.get(key)?.as_i64()
+    }
+
+    pub fn get_array(&self, key: &str) -> Option<Vec<Value>> {
+        self.log_parameter(key, self.get_array_with_exposure_logging_disabled(key))
+    }
+
+    pub fn get_array_with_exposure_logging_disabled(&self, key: &str) -> Option<Vec<Value>> {
+        self.value.get(key)?.as_array().cloned()
+    }
+
+    pub fn get_object(&self, key: &str) -> Option<HashMap<String, Value>> {
+        self.log_parameter(key, self.get_object_with_exposure_logging_disabled(key))
+    }
+
+    pub fn get_object_with_exposure_logging_disabled(
+        &self,
+        key: &str,
+    ) -> Option<HashMap<String, Value>> {
+        let object = self.value.get(key)?.as_object()?;
+        Some(object.clone().into_iter().collect())
+    }
+
+    pub fn keys(&self) -> Vec<&str> {
+        self.value.keys().map(String::as_str).collect()
+    }
+
+    fn log_parameter<T>(&self, key: &str, value: Option<T>) -> Option<T> {
+        let value = value?;
+        if !self.log_data.disable_exposure_logging {
+            if let Some(driver) = self.log_data.driver.upgrade() {
+                driver.log_layer_parameter_exposure(self, key, &self.log_data);
+            }
+        }
+        Some(value)
+    }
 }
 
 pub struct LayerLogData {
     pub(crate) eval_result: EvalResult,
     pub(crate) user: StatsigUser,
+    // The instance that evaluated this layer, so exposures are logged there
+    pub(crate) driver: Weak<StatsigDriver>,
+    pub(crate) disable_exposure_logging: bool,
+}
+
+#[tokio::test]
+async fn test_typed_parameter_getters() {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use crate::statsig::internal::test_utils::{
+        make_config_spec, make_specs, serve_specs, shutdown,
+    };
+
+    let parameters =
+        json!({"s": "text", "b": true, "n": 1.5, "i": 2, "arr": [1], "obj": {"k": "v"}});
+    let layers = vec![make_config_spec("a_layer", "layer", parameters)];
+    let (options, logged) = serve_specs(make_specs(vec![], vec![], layers, 1));
+    let driver = Arc::new(StatsigDriver::new("secret key", options).unwrap());
+    driver.initialize().await;
+    let user = StatsigUser::with_user_id("a-user".to_string());
+
+    let layer = driver.get_layer(&user, "a_layer");
+    assert_eq!(
+        layer.get_string_with_exposure_logging_disabled("s"),
+        Some("text".to_string())
+    );
+    assert_eq!(
+        layer.get_bool_with_exposure_logging_disabled("b"),
+        Some(true)
+    );
+    assert_eq!(layer.get_f64_with_exposure_logging_disabled("n"), Some(1.5));
+    assert_eq!(layer.get_i64_with_exposure_logging_disabled("i"), Some(2));
+    assert_eq!(
+        layer.get_array_with_exposure_logging_disabled("arr"),
+        Some(vec![json!(1)])
+    );
+    let object = layer.get_object_with_exposure_logging_disabled("obj");
+    assert_eq!(object.map(|o| o.len()), Some(1));
+    // Only a read that returns a value logs an exposure
+    assert_eq!(layer.get_bool("s"), None);
+    assert_eq!(layer.get_i64("i"), Some(2));
+
+    shutdown(driver).await;
+    let logged = logged.lock().unwrap();
+    let exposures: Vec<_> = logged
+        .iter()
+        .filter(|event| event["eventName"] == "statsig::layer_exposure")
+        .collect();
+    assert_eq!(exposures.len(), 1);
+    assert_eq!(exposures[0]["metadata"]["parameterName"], "i");
 }
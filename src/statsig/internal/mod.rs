@@ -1,4 +1,6 @@
+pub use data_types::{APICondition, APIRule, APISpec};
 pub use dynamic_config::DynamicConfig;
+pub use evaluation::EvalCache;
 pub use evaluation::EvalResult;
 pub use evaluation::EvalDetails;
 pub use evaluation::EvaluationReason;
@@ -18,3 +20,5 @@ mod statsig_event_internal;
 mod statsig_logger;
 mod statsig_network;
 mod statsig_store;
+#[cfg(test)]
+pub(crate) mod test_utils;
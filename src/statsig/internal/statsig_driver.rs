@@ -1,26 +1,28 @@
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use serde::de::DeserializeOwned;
 use serde_json::{from_value, Value};
 use tokio::runtime::{Builder, Handle, Runtime};
+use tracing::{instrument, warn};
 
 use crate::statsig::internal::statsig_event_internal::{make_config_exposure, make_layer_exposure};
+use crate::statsig::statsig_config_change::ConfigChangeListener;
+use crate::statsig::statsig_initialize_details::InitializeDetails;
 use crate::StatsigUser;
-use crate::{LayerLogData, StatsigEvent, StatsigOptions};
+use crate::{AllEvaluations, EvaluationTrace, StatsigEvent, StatsigOptions};
 
-use super::evaluation::StatsigEvaluator;
+use super::evaluation::{EvalCache, StatsigEvaluator};
 use super::feature_gate::FeatureGate;
 use super::statsig_event_internal::{finalize_event, make_gate_exposure};
 use super::statsig_logger::StatsigLogger;
 use super::statsig_network::StatsigNetwork;
 use super::statsig_store::StatsigStore;
-use super::DynamicConfig;
-use super::Layer;
+use super::{DynamicConfig, EvalResult};
+use super::{Layer, LayerLogData};
 
 pub struct StatsigDriver {
-    pub secret_key: String,
     pub options: StatsigOptions,
     // Stores the tokio runtime if it is owned by the driver and has not yet
     // been shutdown.
@@ -44,13 +46,13 @@ impl StatsigDriver {
             (Some(rt), handle)
         };
 
-        let network = Arc::from(StatsigNetwork::new(secret_key, &options));
+        let network = StatsigNetwork::new(secret_key, &options).map_err(std::io::Error::other)?;
+        let network = Arc::from(network);
         let logger = StatsigLogger::new(&handle, network.clone(), &options);
         let store = Arc::from(StatsigStore::new(&handle, network.clone(), &options));
         let evaluator = StatsigEvaluator::new(store.clone(), &options);
 
         Ok(StatsigDriver {
-            secret_key: secret_key.to_string(),
             options,
             runtime: Mutex::from(opt_runtime),
             store,
@@ -59,9 +61,31 @@ impl StatsigDriver {
         })
     }
 
-    pub async fn initialize(&self) {
-        // bubble up error?
-        self.store.initialize().await;
+    #[instrument(skip_all)]
+    pub async fn initialize(&self) -> InitializeDetails {
+        let start = Instant::now();
+        let init_timeout_ms = self.options.init_timeout_ms;
+
+        let error = self.store.initialize(init_timeout_ms).await;
+
+        // The user agent parser loads in the background. Wait for it with what
+        // is left of the time budget, so early ua_based conditions evaluate.
+        match init_timeout_ms {
+            Some(ms) => {
+                let remaining = Duration::from_millis(ms as u64).saturating_sub(start.elapsed());
+                let _ = tokio::time::timeout(remaining, self.evaluator.initialize()).await;
+            }
+            None => self.evaluator.initialize().await,
+        }
+        let eval_details = self.store.get_eval_details();
+
+        InitializeDetails {
+            duration_ms: start.elapsed().as_millis() as u64,
+            init_success: error.is_none(),
+            source: eval_details.reason,
+            config_sync_time: eval_details.config_sync_time,
+            error,
+        }
     }
 
     pub fn shutdown(&self) {
@@ -76,73 +100,392 @@ impl StatsigDriver {
     }
 
     pub fn check_gate(&self, user: &StatsigUser, gate_name: &str) -> bool {
+        self.eval_gate(user, gate_name, true).bool_value
+    }
+
+    pub fn check_gate_with_exposure_logging_disabled(
+        &self,
+        user: &StatsigUser,
+        gate_name: &str,
+    ) -> bool {
+        self.eval_gate(user, gate_name, false).bool_value
+    }
+
+    pub fn get_feature_gate(&self, user: &StatsigUser, gate_name: &str) -> FeatureGate {
+        let eval_result = self.eval_gate(user, gate_name, true);
+        Self::make_feature_gate(gate_name, eval_result)
+    }
+
+    pub fn get_feature_gate_with_exposure_logging_disabled(
+        &self,
+        user: &StatsigUser,
+        gate_name: &str,
+    ) -> FeatureGate {
+        let eval_result = self.eval_gate(user, gate_name, false);
+        Self::make_feature_gate(gate_name, eval_result)
+    }
+
+    pub fn get_config<T: DeserializeOwned>(
+        &self,
+        user: &StatsigUser,
+        config_name: &str,
+    ) -> DynamicConfig<T> {
+        let eval_result = self.eval_config(user, config_name, true);
+        Self::make_dynamic_config(config_name, eval_result)
+    }
+
+    pub fn get_config_with_exposure_logging_disabled<T: DeserializeOwned>(
+        &self,
+        user: &StatsigUser,
+        config_name: &str,
+    ) -> DynamicConfig<T> {
+        let eval_result = self.eval_config(user, config_name, false);
+        Self::make_dynamic_config(config_name, eval_result)
+    }
+
+    // Keeps the user in the same experiment group while the experiment is
+    // active, using options.user_persistent_storage. Without a storage this
+    // is the same as get_config.
+    pub fn get_experiment_with_persisted_assignment<T: DeserializeOwned>(
+        &self,
+        user: &StatsigUser,
+        experiment_name: &str,
+    ) -> DynamicConfig<T> {
         let normalized_user = &self.get_normalized_user_copy(user);
-        let eval_result = self.evaluator.check_gate(normalized_user, gate_name);
+        let eval_result = match &self.options.user_persistent_storage {
+            Some(storage) => self.evaluator.get_experiment_persisted(
+                normalized_user,
+                experiment_name,
+                storage.as_ref(),
+            ),
+            None => self.evaluator.get_config(normalized_user, experiment_name),
+        };
 
-        self.logger.enqueue(make_gate_exposure(
+        self.logger.enqueue_exposure(make_config_exposure(
+            normalized_user,
+            experiment_name,
+            &eval_result,
+            &self.options.environment,
+            false,
+        ));
+        Self::make_dynamic_config(experiment_name, eval_result)
+    }
+
+    pub fn get_layer(self: &Arc<Self>, user: &StatsigUser, layer_name: &str) -> Layer {
+        self.get_layer_impl(user, layer_name, false)
+    }
+
+    pub fn get_layer_with_exposure_logging_disabled(
+        self: &Arc<Self>,
+        user: &StatsigUser,
+        layer_name: &str,
+    ) -> Layer {
+        self.get_layer_impl(user, layer_name, true)
+    }
+
+    // Used by StatsigUserContext, which normalizes its user up front
+    pub(crate) fn get_feature_gate_with_cache(
+        &self,
+        normalized_user: &StatsigUser,
+        gate_name: &str,
+        cache: &EvalCache,
+    ) -> FeatureGate {
+        let eval_result = self
+            .evaluator
+            .check_gate_cached(normalized_user, gate_name, cache);
+        self.logger.enqueue_exposure(make_gate_exposure(
             normalized_user,
             gate_name,
             &eval_result,
             &self.options.environment,
+            false,
         ));
+        Self::make_feature_gate(gate_name, eval_result)
+    }
 
-        eval_result.bool_value
+    pub(crate) fn get_config_with_cache<T: DeserializeOwned>(
+        &self,
+        normalized_user: &StatsigUser,
+        config_name: &str,
+        cache: &EvalCache,
+    ) -> DynamicConfig<T> {
+        let eval_result = self
+            .evaluator
+            .get_config_cached(normalized_user, config_name, cache);
+        self.logger.enqueue_exposure(make_config_exposure(
+            normalized_user,
+            config_name,
+            &eval_result,
+            &self.options.environment,
+            false,
+        ));
+        Self::make_dynamic_config(config_name, eval_result)
     }
 
-    pub fn get_feature_gate(&self, user: &StatsigUser, gate_name: &str) -> FeatureGate {
+    pub fn evaluate_all(self: &Arc<Self>, user: &StatsigUser) -> AllEvaluations {
+        self.evaluate_all_impl(user, true)
+    }
+
+    pub fn evaluate_all_with_exposure_logging_disabled(
+        self: &Arc<Self>,
+        user: &StatsigUser,
+    ) -> AllEvaluations {
+        self.evaluate_all_impl(user, false)
+    }
+
+    pub fn manually_log_gate_exposure(&self, user: &StatsigUser, gate_name: &str) {
         let normalized_user = &self.get_normalized_user_copy(user);
         let eval_result = self.evaluator.check_gate(normalized_user, gate_name);
-        self.logger.enqueue(make_gate_exposure(
+        self.logger.enqueue_exposure(make_gate_exposure(
             normalized_user,
             gate_name,
             &eval_result,
             &self.options.environment,
+            true,
         ));
+    }
 
-        FeatureGate {
-            value: eval_result.bool_value,
-            name: gate_name.to_string(),
-            rule_id: eval_result.rule_id,
-            evaluation_details: eval_result.evaluation_details
-        }
+    pub fn manually_log_config_exposure(&self, user: &StatsigUser, config_name: &str) {
+        let normalized_user = &self.get_normalized_user_copy(user);
+        let eval_result = self.evaluator.get_config(normalized_user, config_name);
+        self.logger.enqueue_exposure(make_config_exposure(
+            normalized_user,
+            config_name,
+            &eval_result,
+            &self.options.environment,
+            true,
+        ));
     }
 
-    pub fn get_config<T: DeserializeOwned>(
+    pub fn manually_log_layer_parameter_exposure(
         &self,
         user: &StatsigUser,
-        config_name: &str,
-    ) -> DynamicConfig<T> {
+        layer_name: &str,
+        parameter_name: &str,
+    ) {
         let normalized_user = &self.get_normalized_user_copy(user);
-        let eval_result = self.evaluator.get_config(normalized_user, config_name);
-
-        self.logger.enqueue(make_config_exposure(
+        let eval_result = self.evaluator.get_layer(normalized_user, layer_name);
+        self.logger.enqueue_exposure(make_layer_exposure(
             normalized_user,
-            config_name,
+            layer_name,
+            parameter_name,
             &eval_result,
             &self.options.environment,
+            true,
         ));
+    }
 
-        DynamicConfig {
-            name: config_name.to_string(),
-            value: match eval_result.json_value {
-                Some(json_value) => match serde_json::from_value::<T>(json_value) {
-                    Err(e) => {
-                        eprintln!("[Statsig] Failed to deserialize config value: {}", e);
-                        None
-                    }
-                    Ok(deserialized) => Some(deserialized),
-                },
-                None => None,
-            },
-            rule_id: eval_result.rule_id,
-            evaluation_details: eval_result.evaluation_details
+    pub fn log_event(&self, user: &StatsigUser, event: StatsigEvent) {
+        self.logger
+            .enqueue(finalize_event(user, event, &self.options.environment))
+    }
+
+    pub fn add_config_change_listener(&self, listener: ConfigChangeListener) {
+        self.store.add_config_change_listener(listener);
+    }
+
+    pub fn get_dropped_event_count(&self) -> u64 {
+        self.logger.get_dropped_event_count()
+    }
+
+    pub fn get_client_initialize_response(&self, user: &StatsigUser) -> Value {
+        let normalized_user = self.get_normalized_user_copy(user);
+        self.evaluator
+            .get_client_initialize_response(&normalized_user)
+    }
+
+    pub fn explain_gate(&self, user: &StatsigUser, gate_name: &str) -> EvaluationTrace {
+        let normalized_user = self.get_normalized_user_copy(user);
+        self.evaluator.explain_gate(&normalized_user, gate_name)
+    }
+
+    pub fn explain_config(&self, user: &StatsigUser, config_name: &str) -> EvaluationTrace {
+        let normalized_user = self.get_normalized_user_copy(user);
+        self.evaluator.explain_config(&normalized_user, config_name)
+    }
+
+    pub fn override_gate(&self, gate_name: &str, value: bool, id: Option<&str>) {
+        self.evaluator
+            .local_overrides
+            .override_gate(gate_name, value, id);
+    }
+
+    pub fn override_config(&self, config_name: &str, value: Value, id: Option<&str>) {
+        self.evaluator
+            .local_overrides
+            .override_config(config_name, value, id);
+    }
+
+    pub fn override_layer(&self, layer_name: &str, value: Value, id: Option<&str>) {
+        self.evaluator
+            .local_overrides
+            .override_layer(layer_name, value, id);
+    }
+
+    pub fn remove_gate_override(&self, gate_name: &str, id: Option<&str>) {
+        self.evaluator
+            .local_overrides
+            .remove_gate_override(gate_name, id);
+    }
+
+    pub fn remove_config_override(&self, config_name: &str, id: Option<&str>) {
+        self.evaluator
+            .local_overrides
+            .remove_config_override(config_name, id);
+    }
+
+    pub fn remove_layer_override(&self, layer_name: &str, id: Option<&str>) {
+        self.evaluator
+            .local_overrides
+            .remove_layer_override(layer_name, id);
+    }
+
+    pub fn remove_all_overrides(&self) {
+        self.evaluator.local_overrides.remove_all_overrides();
+    }
+
+    pub(crate) fn log_layer_parameter_exposure(
+        &self,
+        layer: &Layer,
+        parameter_name: &str,
+        log_data: &LayerLogData,
+    ) {
+        self.logger.enqueue_exposure(make_layer_exposure(
+            &log_data.user,
+            &layer.name,
+            parameter_name,
+            &log_data.eval_result,
+            &self.options.environment,
+            false,
+        ));
+    }
+
+    fn eval_gate(&self, user: &StatsigUser, gate_name: &str, log_exposure: bool) -> EvalResult {
+        let normalized_user = &self.get_normalized_user_copy(user);
+        let eval_result = self.evaluator.check_gate(normalized_user, gate_name);
+
+        if log_exposure {
+            self.logger.enqueue_exposure(make_gate_exposure(
+                normalized_user,
+                gate_name,
+                &eval_result,
+                &self.options.environment,
+                false,
+            ));
         }
+
+        eval_result
+    }
+
+    fn eval_config(&self, user: &StatsigUser, config_name: &str, log_exposure: bool) -> EvalResult {
+        let normalized_user = &self.get_normalized_user_copy(user);
+        let eval_result = self.evaluator.get_config(normalized_user, config_name);
+
+        if log_exposure {
+            self.logger.enqueue_exposure(make_config_exposure(
+                normalized_user,
+                config_name,
+                &eval_result,
+                &self.options.environment,
+                false,
+            ));
+        }
+
+        eval_result
     }
 
-    pub fn get_layer(&self, user: &StatsigUser, layer_name: &str) -> Layer {
+    fn evaluate_all_impl(
+        self: &Arc<Self>,
+        user: &StatsigUser,
+        log_exposure: bool,
+    ) -> AllEvaluations {
+        let normalized_user = self.get_normalized_user_copy(user);
+        let results = self.evaluator.evaluate_all(&normalized_user);
+
+        let mut gates = HashMap::new();
+        for (gate_name, eval_result) in results.gates {
+            if log_exposure {
+                self.logger.enqueue_exposure(make_gate_exposure(
+                    &normalized_user,
+                    &gate_name,
+                    &eval_result,
+                    &self.options.environment,
+                    false,
+                ));
+            }
+            let gate = Self::make_feature_gate(&gate_name, eval_result);
+            gates.insert(gate_name, gate);
+        }
+
+        let mut layers = HashMap::new();
+        for (layer_name, eval_result) in results.layers {
+            let layer = self.make_layer(
+                normalized_user.clone(),
+                &layer_name,
+                eval_result,
+                !log_exposure,
+            );
+            layers.insert(layer_name, layer);
+        }
+
+        AllEvaluations {
+            gates,
+            configs: self.make_dynamic_configs(&normalized_user, results.configs, log_exposure),
+            experiments: self.make_dynamic_configs(
+                &normalized_user,
+                results.experiments,
+                log_exposure,
+            ),
+            layers,
+        }
+    }
+
+    fn make_dynamic_configs(
+        &self,
+        normalized_user: &StatsigUser,
+        results: Vec<(String, EvalResult)>,
+        log_exposure: bool,
+    ) -> HashMap<String, DynamicConfig<Value>> {
+        let mut configs = HashMap::new();
+        for (config_name, eval_result) in results {
+            if log_exposure {
+                self.logger.enqueue_exposure(make_config_exposure(
+                    normalized_user,
+                    &config_name,
+                    &eval_result,
+                    &self.options.environment,
+                    false,
+                ));
+            }
+            let config = Self::make_dynamic_config(&config_name, eval_result);
+            configs.insert(config_name, config);
+        }
+        configs
+    }
+
+    fn get_layer_impl(
+        self: &Arc<Self>,
+        user: &StatsigUser,
+        layer_name: &str,
+        disable_exposure_logging: bool,
+    ) -> Layer {
         let normalized_user = self.get_normalized_user_copy(user);
         let eval_result = self.evaluator.get_layer(&normalized_user, layer_name);
+        self.make_layer(
+            normalized_user,
+            layer_name,
+            eval_result,
+            disable_exposure_logging,
+        )
+    }
 
+    fn make_layer(
+        self: &Arc<Self>,
+        normalized_user: StatsigUser,
+        layer_name: &str,
+        eval_result: EvalResult,
+        disable_exposure_logging: bool,
+    ) -> Layer {
         let mut value = HashMap::from([]);
         if let Some(ref json_value) = eval_result.json_value {
             if let Ok(deserialized) = from_value(json_value.clone()) {
@@ -158,37 +501,46 @@ impl StatsigDriver {
             log_data: LayerLogData {
                 user: normalized_user,
                 eval_result,
+                driver: Arc::downgrade(self),
+                disable_exposure_logging,
             },
         }
     }
 
-    pub fn log_event(&self, user: &StatsigUser, event: StatsigEvent) {
-        self.logger
-            .enqueue(finalize_event(user, event, &self.options.environment))
-    }
-
-    pub fn get_client_initialize_response(&self, user: &StatsigUser) -> Value {
-        let normalized_user = self.get_normalized_user_copy(user);
-        self.evaluator
-            .get_client_initialize_response(&normalized_user)
+    fn make_feature_gate(gate_name: &str, eval_result: EvalResult) -> FeatureGate {
+        FeatureGate {
+            value: eval_result.bool_value,
+            name: gate_name.to_string(),
+            rule_id: eval_result.rule_id,
+            evaluation_details: eval_result.evaluation_details
+        }
     }
 
-    pub(crate) fn log_layer_parameter_exposure(
-        &self,
-        layer: &Layer,
-        parameter_name: &str,
-        log_data: &LayerLogData,
-    ) {
-        self.logger.enqueue(make_layer_exposure(
-            &log_data.user,
-            &layer.name,
-            parameter_name,
-            &log_data.eval_result,
-            &self.options.environment,
-        ));
+    fn make_dynamic_config<T: DeserializeOwned>(
+        config_name: &str,
+        eval_result: EvalResult,
+    ) -> DynamicConfig<T> {
+        DynamicConfig {
+            name: config_name.to_string(),
+            value: match eval_result.json_value {
+                Some(json_value) => match serde_json::from_value::<T>(json_value) {
+                    Err(e) => {
+                        warn!(
+                            config = config_name,
+                            "Failed to deserialize config value: {}", e
+                        );
+                        None
+                    }
+                    Ok(deserialized) => Some(deserialized),
+                },
+                None => None,
+            },
+            rule_id: eval_result.rule_id,
+            evaluation_details: eval_result.evaluation_details
+        }
     }
 
-    fn get_normalized_user_copy(&self, user: &StatsigUser) -> StatsigUser {
+    pub(crate) fn get_normalized_user_copy(&self, user: &StatsigUser) -> StatsigUser {
         let mut normalized_user = user.clone();
         if self.options.environment.is_some() {
             normalized_user.statsig_environment = self.options.environment.clone();
@@ -210,5 +562,5 @@ impl StatsigDriver {
 // `tokio::test` sets up an existing runtime, likely how most users of this library will use it.
 #[tokio::test]
 async fn test_driver_cleanup_doesnt_panic() {
-    StatsigDriver::new(&"secret key", StatsigOptions::default()).unwrap();
+    StatsigDriver::new("secret key", StatsigOptions::default()).unwrap();
 }
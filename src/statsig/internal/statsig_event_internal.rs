@@ -27,16 +27,18 @@ pub(crate) fn make_gate_exposure(
     gate_name: &str,
     eval_result: &EvalResult,
     statsig_environment: &StatsigEnvironment,
+    is_manual_exposure: bool,
 ) -> StatsigEventInternal {
-    let mut metadata = make_metadata_for_exposure("gate", gate_name, eval_result);
+    let mut metadata =
+        make_metadata_for_exposure("gate", gate_name, eval_result, is_manual_exposure);
     metadata.extend(HashMap::from([(
         "gateValue".to_string(),
         json!(eval_result.bool_value.to_string()),
     )]));
-    if eval_result.config_version != None {
+    if let Some(config_version) = eval_result.config_version {
         metadata.extend(HashMap::from([(
             "configVersion".to_string(),
-            json!(eval_result.config_version.unwrap().to_string()),
+            json!(config_version.to_string()),
         )]));
     }
     let event = StatsigEvent {
@@ -58,20 +60,18 @@ pub(crate) fn make_config_exposure(
     config_name: &str,
     eval_result: &EvalResult,
     statsig_environment: &StatsigEnvironment,
+    is_manual_exposure: bool,
 ) -> StatsigEventInternal {
-    let mut metadata = make_metadata_for_exposure(
-        "config",
-        config_name,
-        eval_result,
-    );
+    let mut metadata =
+        make_metadata_for_exposure("config", config_name, eval_result, is_manual_exposure);
     metadata.extend(HashMap::from([(
         "rulePassed".to_string(),
         json!(eval_result.bool_value.to_string()),
     )]));
-    if eval_result.config_version != None {
+    if let Some(config_version) = eval_result.config_version {
         metadata.extend(HashMap::from([(
             "configVersion".to_string(),
-            json!(eval_result.config_version.unwrap().to_string()),
+            json!(config_version.to_string()),
         )]));
     }
     let event = StatsigEvent {
@@ -94,6 +94,7 @@ pub(crate) fn make_layer_exposure(
     parameter_name: &str,
     eval_result: &EvalResult,
     statsig_environment: &StatsigEnvironment,
+    is_manual_exposure: bool,
 ) -> StatsigEventInternal {
     let mut exposures = &eval_result.undelegated_secondary_exposures;
     let mut allocated_experiment = None;
@@ -106,7 +107,8 @@ pub(crate) fn make_layer_exposure(
         allocated_experiment = eval_result.config_delegate.clone();
         exposures = &eval_result.secondary_exposures;
     }
-    let mut metadata = make_metadata_for_exposure("config", layer_name, eval_result);
+    let mut metadata =
+        make_metadata_for_exposure("config", layer_name, eval_result, is_manual_exposure);
     metadata.extend(HashMap::from([
         (
             "allocatedExperiment".to_string(),
@@ -118,10 +120,10 @@ pub(crate) fn make_layer_exposure(
             json!(format!("{}", is_explicit)),
         ),
     ]));
-    if eval_result.config_version != None {
+    if let Some(config_version) = eval_result.config_version {
         metadata.extend(HashMap::from([(
             "configVersion".to_string(),
-            json!(eval_result.config_version.unwrap().to_string()),
+            json!(config_version.to_string()),
         )]));
     }
     let event = StatsigEvent {
@@ -181,8 +183,9 @@ fn make_metadata_for_exposure(
     config_key: &str,
     config_name: &str,
     eval_result: &EvalResult,
+    is_manual_exposure: bool,
 ) -> HashMap<String, Value, RandomState> {
-    HashMap::from([
+    let mut metadata = HashMap::from([
         (config_key.to_string(), json!(config_name)),
         ("ruleID".to_string(), json!(eval_result.rule_id)),
         (
@@ -201,5 +204,11 @@ fn make_metadata_for_exposure(
             "serverTime".to_string(),
             json!(eval_result.evaluation_details.server_time),
         ),
-    ])
+    ]);
+
+    if is_manual_exposure {
+        metadata.insert("isManualExposure".to_string(), json!("true"));
+    }
+
+    metadata
 }
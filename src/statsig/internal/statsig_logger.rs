@@ -1,24 +1,55 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, RwLock};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use tokio::runtime::Handle;
 use tokio::task::JoinHandle;
+use tracing::{instrument, warn};
 
 use crate::statsig::internal::statsig_network::StatsigNetwork;
 use crate::StatsigOptions;
 
 use super::statsig_event_internal::StatsigEventInternal;
 
+type EventBatch = Vec<StatsigEventInternal>;
+
+// Bounds the failed batch buffer by events, since batch sizes vary with
+// logger_max_queue_size and manual flushes
+const MAX_FAILED_EVENTS: usize = 5_000;
+const MAX_SEND_ATTEMPTS: u32 = 3;
+const INITIAL_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+const RETRYABLE_STATUS_CODES: [u16; 9] = [408, 429, 500, 502, 503, 504, 522, 524, 599];
+
+const DEDUPE_MAX_KEYS: usize = 10_000;
+const DEDUPE_RESET_INTERVAL: Duration = Duration::from_secs(60);
+
+// Metadata fields that identify an exposure. Timing fields are left out so
+// repeat evaluations of the same result produce the same key.
+const DEDUPE_METADATA_FIELDS: [&str; 7] = [
+    "gate",
+    "config",
+    "ruleID",
+    "gateValue",
+    "rulePassed",
+    "parameterName",
+    "allocatedExperiment",
+];
+
 pub struct StatsigLogger {
     runtime_handle: Handle,
     network: Arc<StatsigNetwork>,
-    events: Arc<RwLock<Vec<StatsigEventInternal>>>,
+    events: Arc<RwLock<EventBatch>>,
+    // Batches that could not be delivered, retried on the next flush
+    failed_batches: Arc<RwLock<Vec<EventBatch>>>,
+    dropped_event_count: Arc<AtomicU64>,
     max_queue_size: usize,
     flush_interval_ms: u32,
     bg_thread_handle: Option<JoinHandle<()>>,
     running_jobs: Arc<RwLock<Vec<JoinHandle<()>>>>,
     is_shutdown: Arc<AtomicBool>,
+    local_mode: bool,
+    deduper: RwLock<ExposureDeduper>,
 }
 
 impl StatsigLogger {
@@ -31,17 +62,45 @@ impl StatsigLogger {
             runtime_handle: runtime_handle.clone(),
             network,
             events: Arc::from(RwLock::from(vec![])),
+            failed_batches: Arc::from(RwLock::from(vec![])),
+            dropped_event_count: Arc::new(AtomicU64::new(0)),
             max_queue_size: options.logger_max_queue_size as usize,
             flush_interval_ms: options.logger_flush_interval_ms,
             running_jobs: Arc::from(RwLock::from(vec![])),
             bg_thread_handle: None,
             is_shutdown: Arc::new(AtomicBool::new(false)),
+            local_mode: options.local_mode,
+            deduper: RwLock::new(ExposureDeduper::new()),
         };
-        inst.spawn_bg_thread();
+        if !inst.local_mode {
+            inst.spawn_bg_thread();
+        }
         inst
     }
 
+    // Exposures go through here so repeats within the dedupe window are
+    // dropped. Custom events should use `enqueue` directly.
+    pub fn enqueue_exposure(&self, event: StatsigEventInternal) {
+        if let Some(key) = ExposureDeduper::get_key(&event) {
+            // Most exposures are repeats, so check under the read lock first
+            // and only take the write lock to record a new one
+            let seen = self
+                .deduper
+                .read()
+                .is_ok_and(|deduper| deduper.contains(&key));
+            if seen || self.deduper.write().is_ok_and(|mut d| !d.insert(key)) {
+                return;
+            }
+        }
+
+        self.enqueue(event);
+    }
+
     pub fn enqueue(&self, event: StatsigEventInternal) {
+        if self.local_mode {
+            return;
+        }
+
         let mut should_flush = false;
         if let Ok(mut mut_events) = self.events.write() {
             mut_events.push(event);
@@ -53,24 +112,31 @@ impl StatsigLogger {
         }
     }
 
+    pub fn get_dropped_event_count(&self) -> u64 {
+        self.dropped_event_count.load(Ordering::Relaxed)
+    }
+
     pub fn flush(&self) {
         let events = self.events.clone();
+        let failed_batches = self.failed_batches.clone();
+        let dropped_event_count = self.dropped_event_count.clone();
         let network = self.network.clone();
 
         if let Ok(mut lock) = self.running_jobs.write() {
             // Clear any finished jobs
             lock.retain(|x| !x.is_finished());
 
-            lock.push(
-                self.runtime_handle
-                    .spawn(async move { Self::flush_impl(&network, &events).await }),
-            );
+            lock.push(self.runtime_handle.spawn(async move {
+                Self::flush_impl(&network, &events, &failed_batches, &dropped_event_count).await
+            }));
         }
     }
 
     pub fn shutdown(&self) {
         self.is_shutdown.store(true, Ordering::Relaxed);
         let events = self.events.clone();
+        let failed_batches = self.failed_batches.clone();
+        let dropped_event_count = self.dropped_event_count.clone();
         let network = self.network.clone();
 
         #[allow(clippy::await_holding_lock)]
@@ -83,11 +149,27 @@ impl StatsigLogger {
                     let _ = handle.await;
                 }
             }
-            Self::flush_impl(&network, &events).await;
+            Self::flush_impl(&network, &events, &failed_batches, &dropped_event_count).await;
         });
+
+        // Nothing will retry these after shutdown
+        if let Ok(mut lock) = self.failed_batches.write() {
+            let count: usize = std::mem::take(&mut *lock).iter().map(Vec::len).sum();
+            if count > 0 {
+                warn!("Dropping {} undelivered events on shutdown", count);
+                self.dropped_event_count
+                    .fetch_add(count as u64, Ordering::Relaxed);
+            }
+        }
     }
 
-    async fn flush_impl(network: &StatsigNetwork, events: &RwLock<Vec<StatsigEventInternal>>) {
+    #[instrument(name = "flush", skip_all)]
+    async fn flush_impl(
+        network: &StatsigNetwork,
+        events: &RwLock<EventBatch>,
+        failed_batches: &RwLock<Vec<EventBatch>>,
+        dropped_event_count: &AtomicU64,
+    ) {
         let count = match events.read().ok() {
             Some(e) => e.len(),
             _ => return,
@@ -101,13 +183,83 @@ impl StatsigLogger {
             }
         }
 
+        // Retry anything that failed previously along with the new events
+        let mut batches = match failed_batches.write() {
+            Ok(mut lock) => std::mem::take(&mut *lock),
+            _ => vec![],
+        };
         if let Some(local_events) = local_events {
-            let _ = network.send_events(local_events).await;
+            batches.push(local_events);
+        }
+
+        for batch in batches {
+            match Self::send_with_retries(network, &batch).await {
+                SendOutcome::Sent => {}
+                SendOutcome::Retryable => {
+                    Self::buffer_failed_batch(batch, failed_batches, dropped_event_count)
+                }
+                SendOutcome::Rejected => {
+                    warn!("Dropping {} events rejected by log_event", batch.len());
+                    dropped_event_count.fetch_add(batch.len() as u64, Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    async fn send_with_retries(
+        network: &StatsigNetwork,
+        batch: &[StatsigEventInternal],
+    ) -> SendOutcome {
+        let mut backoff = INITIAL_RETRY_BACKOFF;
+        for attempt in 1..=MAX_SEND_ATTEMPTS {
+            let status = network.send_events(batch).await.map(|res| res.status);
+            match status {
+                Some(code) if code < 300 => return SendOutcome::Sent,
+                Some(code) if !RETRYABLE_STATUS_CODES.contains(&code) => {
+                    return SendOutcome::Rejected
+                }
+                _ => {}
+            }
+
+            if attempt < MAX_SEND_ATTEMPTS {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+        }
+        SendOutcome::Retryable
+    }
+
+    fn buffer_failed_batch(
+        batch: EventBatch,
+        failed_batches: &RwLock<Vec<EventBatch>>,
+        dropped_event_count: &AtomicU64,
+    ) {
+        let mut lock = match failed_batches.write() {
+            Ok(lock) => lock,
+            _ => {
+                dropped_event_count.fetch_add(batch.len() as u64, Ordering::Relaxed);
+                return;
+            }
+        };
+
+        lock.push(batch);
+
+        let mut buffered: usize = lock.iter().map(|batch| batch.len()).sum();
+        while buffered > MAX_FAILED_EVENTS {
+            let oldest = lock.remove(0);
+            warn!(
+                "Failed event buffer is full, dropping {} events",
+                oldest.len()
+            );
+            dropped_event_count.fetch_add(oldest.len() as u64, Ordering::Relaxed);
+            buffered -= oldest.len();
         }
     }
 
     fn spawn_bg_thread(&mut self) {
         let events = self.events.clone();
+        let failed_batches = self.failed_batches.clone();
+        let dropped_event_count = self.dropped_event_count.clone();
         let network = self.network.clone();
         let interval = Duration::from_millis(self.flush_interval_ms as u64);
         let is_shutdown = self.is_shutdown.clone();
@@ -117,9 +269,364 @@ impl StatsigLogger {
                 if is_shutdown.load(Ordering::Relaxed) {
                     break;
                 }
-                Self::flush_impl(&network, &events).await;
+                Self::flush_impl(&network, &events, &failed_batches, &dropped_event_count).await;
                 tokio::time::sleep(interval).await;
             }
         }));
     }
 }
+
+enum SendOutcome {
+    Sent,
+    // Failed with a connection error or retryable status; keep the batch
+    Retryable,
+    // Failed with a status that will not succeed on retry; drop the batch
+    Rejected,
+}
+
+struct ExposureDeduper {
+    keys: HashSet<String>,
+    last_reset: Instant,
+}
+
+impl ExposureDeduper {
+    fn new() -> Self {
+        ExposureDeduper {
+            keys: HashSet::new(),
+            last_reset: Instant::now(),
+        }
+    }
+
+    fn contains(&self, key: &str) -> bool {
+        !self.should_reset() && self.keys.contains(key)
+    }
+
+    // Records the key and returns whether it was not already seen in the
+    // current window.
+    fn insert(&mut self, key: String) -> bool {
+        if self.should_reset() {
+            self.keys.clear();
+            self.last_reset = Instant::now();
+        }
+
+        self.keys.insert(key)
+    }
+
+    fn should_reset(&self) -> bool {
+        self.last_reset.elapsed() >= DEDUPE_RESET_INTERVAL || self.keys.len() >= DEDUPE_MAX_KEYS
+    }
+
+    // None if the exposure should always be logged. Manual exposures are
+    // explicit requests to log, so they are never deduped.
+    fn get_key(event: &StatsigEventInternal) -> Option<String> {
+        let event_name = &event.event_data.event_name;
+        let metadata = event.event_data.metadata.as_ref()?;
+        if metadata.contains_key("isManualExposure") {
+            return None;
+        }
+        let user = &event.user;
+
+        let mut parts = vec![event_name.clone(), user.user_id.clone().unwrap_or_default()];
+
+        if let Some(custom_ids) = &user.custom_ids {
+            let mut ids: Vec<String> = custom_ids
+                .iter()
+                .map(|(id_type, id)| format!("{}:{}", id_type, id))
+                .collect();
+            ids.sort();
+            parts.append(&mut ids);
+        }
+
+        for field in DEDUPE_METADATA_FIELDS {
+            if let Some(value) = metadata.get(field) {
+                parts.push(format!("{}:{}", field, value));
+            }
+        }
+
+        Some(parts.join("|"))
+    }
+}
+
+#[test]
+fn test_exposure_deduper() {
+    use std::collections::HashMap;
+
+    use serde_json::json;
+
+    use crate::statsig::internal::statsig_event_internal::finalize_event;
+    use crate::{StatsigEvent, StatsigUser};
+
+    let make_event = |user_id: &str, rule_id: &str, is_manual: bool| {
+        let mut metadata = HashMap::from([
+            ("gate".to_string(), json!("a_gate")),
+            ("ruleID".to_string(), json!(rule_id)),
+        ]);
+        if is_manual {
+            metadata.insert("isManualExposure".to_string(), json!("true"));
+        }
+        let event = StatsigEvent {
+            event_name: "statsig::gate_exposure".to_string(),
+            value: None,
+            metadata: Some(metadata),
+        };
+        finalize_event(
+            &StatsigUser::with_user_id(user_id.to_string()),
+            event,
+            &None,
+        )
+    };
+    let mut deduper = ExposureDeduper::new();
+    let mut is_duplicate = |event: StatsigEventInternal| match ExposureDeduper::get_key(&event) {
+        Some(key) => deduper.contains(&key) || !deduper.insert(key),
+        None => false,
+    };
+
+    assert!(!is_duplicate(make_event("a-user", "rule_a", false)));
+    assert!(is_duplicate(make_event("a-user", "rule_a", false)));
+    assert!(!is_duplicate(make_event("a-user", "rule_b", false)));
+    assert!(!is_duplicate(make_event("b-user", "rule_a", false)));
+    assert!(!is_duplicate(make_event("a-user", "rule_a", true)));
+    assert!(!is_duplicate(make_event("a-user", "rule_a", true)));
+}
+
+#[tokio::test]
+async fn test_send_retries_only_retryable_statuses() {
+    use async_trait::async_trait;
+
+    use crate::{NetworkProvider, NetworkRequest, NetworkResponse};
+
+    struct StatusProvider {
+        status: u16,
+        attempts: AtomicU64,
+    }
+
+    #[async_trait]
+    impl NetworkProvider for StatusProvider {
+        async fn send(&self, _request: NetworkRequest) -> Option<NetworkResponse> {
+            self.attempts.fetch_add(1, Ordering::Relaxed);
+            Some(NetworkResponse {
+                status: self.status,
+                body: String::new(),
+            })
+        }
+    }
+
+    let send = |status: u16| async move {
+        let provider = Arc::new(StatusProvider {
+            status,
+            attempts: AtomicU64::new(0),
+        });
+        let options = StatsigOptions {
+            network_provider: Some(provider.clone()),
+            ..StatsigOptions::default()
+        };
+        let network = StatsigNetwork::new("secret key", &options).unwrap();
+        let outcome = StatsigLogger::send_with_retries(&network, &[]).await;
+        (outcome, provider.attempts.load(Ordering::Relaxed))
+    };
+
+    let (outcome, attempts) = send(503).await;
+    assert!(matches!(outcome, SendOutcome::Retryable));
+    assert_eq!(attempts, MAX_SEND_ATTEMPTS as u64);
+
+    let (outcome, attempts) = send(400).await;
+    assert!(matches!(outcome, SendOutcome::Rejected));
+    assert_eq!(attempts, 1);
+
+    let (outcome, attempts) = send(202).await;
+    assert!(matches!(outcome, SendOutcome::Sent));
+    assert_eq!(attempts, 1);
+}
+
+#[test]
+fn test_failed_batches_are_bounded_by_event_count() {
+    use crate::statsig::internal::statsig_event_internal::finalize_event;
+    use crate::{StatsigEvent, StatsigUser};
+
+    let make_batch = |size: usize| -> EventBatch {
+        let user = StatsigUser::with_user_id("a-user".to_string());
+        (0..size)
+            .map(|_| {
+                let event = StatsigEvent {
+                    event_name: "an_event".to_string(),
+                    value: None,
+                    metadata: None,
+                };
+                finalize_event(&user, event, &None)
+            })
+            .collect()
+    };
+
+    let failed_batches = RwLock::new(vec![]);
+    let dropped_event_count = AtomicU64::new(0);
+    let batch_size = MAX_FAILED_EVENTS / 4;
+    for _ in 0..4 {
+        StatsigLogger::buffer_failed_batch(
+            make_batch(batch_size),
+            &failed_batches,
+            &dropped_event_count,
+        );
+    }
+    assert_eq!(dropped_event_count.load(Ordering::Relaxed), 0);
+
+    // Many small batches count the same as one large one
+    for _ in 0..10 {
+        StatsigLogger::buffer_failed_batch(make_batch(1), &failed_batches, &dropped_event_count);
+    }
+    assert_eq!(
+        dropped_event_count.load(Ordering::Relaxed),
+        batch_size as u64
+    );
+    let buffered: usize = failed_batches.read().unwrap().iter().map(Vec::len).sum();
+    assert_eq!(buffered, MAX_FAILED_EVENTS - batch_size + 10);
+}
+
+#[tokio::test]
+async fn test_disabled_variants_log_no_exposures() {
+    use serde_json::{json, Value};
+
+    use crate::statsig::internal::test_utils::{
+        make_config_spec, make_gate_spec, make_specs, public_conditions, serve_specs, shutdown,
+    };
+    use crate::statsig::internal::StatsigDriver;
+    use crate::StatsigUser;
+
+    let gates = vec![make_gate_spec("a_gate", public_conditions())];
+    let configs = vec![make_config_spec(
+        "a_config",
+        "dynamic_config",
+        json!({"a": 1}),
+    )];
+    let layers = vec![make_config_spec("a_layer", "layer", json!({"b": 2}))];
+    let (options, logged) = serve_specs(make_specs(gates, configs, layers, 1));
+    let driver = Arc::new(StatsigDriver::new("secret key", options).unwrap());
+    driver.initialize().await;
+    let user = StatsigUser::with_user_id("a-user".to_string());
+
+    assert!(driver.check_gate_with_exposure_logging_disabled(&user, "a_gate"));
+    assert!(
+        driver
+            .get_feature_gate_with_exposure_logging_disabled(&user, "a_gate")
+            .value
+    );
+    let config = driver.get_config_with_exposure_logging_disabled::<Value>(&user, "a_config");
+    assert_eq!(config.value, Some(json!({"a": 1})));
+    driver.evaluate_all_with_exposure_logging_disabled(&user);
+
+    shutdown(driver).await;
+    assert!(logged.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_manual_exposures_are_never_deduped() {
+    use serde_json::json;
+
+    use crate::statsig::internal::test_utils::{
+        event_names, make_config_spec, make_gate_spec, make_specs, public_conditions, serve_specs,
+        shutdown,
+    };
+    use crate::statsig::internal::StatsigDriver;
+    use crate::StatsigUser;
+
+    let gates = vec![make_gate_spec("a_gate", public_conditions())];
+    let configs = vec![make_config_spec(
+        "a_config",
+        "dynamic_config",
+        json!({"a": 1}),
+    )];
+    let layers = vec![make_config_spec("a_layer", "layer", json!({"b": 2}))];
+    let (options, logged) = serve_specs(make_specs(gates, configs, layers, 1));
+    let driver = Arc::new(StatsigDriver::new("secret key", options).unwrap());
+    driver.initialize().await;
+    let user = StatsigUser::with_user_id("a-user".to_string());
+
+    for _ in 0..2 {
+        driver.manually_log_gate_exposure(&user, "a_gate");
+        driver.manually_log_config_exposure(&user, "a_config");
+        driver.manually_log_layer_parameter_exposure(&user, "a_layer", "b");
+    }
+
+    shutdown(driver).await;
+    let exposures = [
+        "statsig::gate_exposure",
+        "statsig::config_exposure",
+        "statsig::layer_exposure",
+    ];
+    assert_eq!(event_names(&logged), [exposures, exposures].concat());
+    for event in logged.lock().unwrap().iter() {
+        assert_eq!(event["metadata"]["isManualExposure"], "true");
+    }
+}
+
+#[tokio::test]
+async fn test_layer_with_logging_disabled_logs_nothing() {
+    use serde_json::json;
+
+    use crate::statsig::internal::test_utils::{
+        make_config_spec, make_specs, serve_specs, shutdown,
+    };
+    use crate::statsig::internal::StatsigDriver;
+    use crate::StatsigUser;
+
+    let parameters = json!({"s": "text", "b": true, "n": 1.5, "i": 2, "arr": [1], "obj": {}});
+    let layers = vec![make_config_spec("a_layer", "layer", parameters)];
+    let (options, logged) = serve_specs(make_specs(vec![], vec![], layers, 1));
+    let driver = Arc::new(StatsigDriver::new("secret key", options).unwrap());
+    driver.initialize().await;
+    let user = StatsigUser::with_user_id("a-user".to_string());
+
+    let layer = driver.get_layer_with_exposure_logging_disabled(&user, "a_layer");
+    assert_eq!(layer.get("s", String::new()), "text");
+    assert_eq!(layer.get_value("s"), Some(json!("text")));
+    assert_eq!(layer.get_string("s"), Some("text".to_string()));
+    assert_eq!(layer.get_bool("b"), Some(true));
+    assert_eq!(layer.get_f64("n"), Some(1.5));
+    assert_eq!(layer.get_i64("i"), Some(2));
+    assert_eq!(layer.get_array("arr"), Some(vec![json!(1)]));
+    assert!(layer.get_object("obj").is_some());
+
+    shutdown(driver).await;
+    assert!(logged.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_shutdown_counts_undelivered_events_as_dropped() {
+    use async_trait::async_trait;
+
+    use crate::statsig::internal::test_utils::shutdown;
+    use crate::statsig::internal::StatsigDriver;
+    use crate::{NetworkProvider, NetworkRequest, NetworkResponse, StatsigEvent, StatsigUser};
+
+    struct UnavailableProvider {}
+
+    #[async_trait]
+    impl NetworkProvider for UnavailableProvider {
+        async fn send(&self, _request: NetworkRequest) -> Option<NetworkResponse> {
+            Some(NetworkResponse {
+                status: 503,
+                body: String::new(),
+            })
+        }
+    }
+
+    let options = StatsigOptions {
+        network_provider: Some(Arc::new(UnavailableProvider {})),
+        ..StatsigOptions::default()
+    };
+    let driver = Arc::new(StatsigDriver::new("secret key", options).unwrap());
+    // Let the background flush make its first pass while the queue is empty,
+    // so the events below are only sent by shutdown
+    tokio::task::yield_now().await;
+    let user = StatsigUser::with_user_id("a-user".to_string());
+    for _ in 0..2 {
+        let event = StatsigEvent {
+            event_name: "an_event".to_string(),
+            value: None,
+            metadata: None,
+        };
+        driver.log_event(&user, event);
+    }
+
+    shutdown(driver.clone()).await;
+    assert_eq!(driver.get_dropped_event_count(), 2);
+}
@@ -1,104 +1,212 @@
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
 
-use http::HeaderMap;
-use reqwest::{Client, Error, Response};
 use serde_json::{json, Value};
+use tracing::warn;
 
 use crate::statsig::internal::statsig_event_internal::StatsigEventInternal;
+use crate::statsig::statsig_network_provider::{
+    NetworkEndpoint, NetworkMethod, NetworkProvider, NetworkRequest, NetworkResponse,
+    ReqwestNetworkProvider,
+};
 use crate::StatsigOptions;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
+// Long enough for the server to end a long-poll before we give up on it
+const LONG_POLL_TIMEOUT: Duration = Duration::from_secs(5 * 60);
 
 pub struct StatsigNetwork {
-    client: Client,
+    provider: Arc<dyn NetworkProvider>,
     secret: String,
     base_api: String,
     dcs_api: String,
+    long_poll_api: Option<String>,
     statsig_metadata: Value,
 }
 
 impl StatsigNetwork {
-    pub fn new(secret_key: &str, options: &StatsigOptions) -> Self {
-        StatsigNetwork {
-            client: Client::new(),
+    pub fn new(secret_key: &str, options: &StatsigOptions) -> Result<Self, String> {
+        Ok(StatsigNetwork {
+            provider: match &options.network_provider {
+                Some(provider) => provider.clone(),
+                None => Arc::new(ReqwestNetworkProvider::from_options(options)?),
+            },
             secret: secret_key.to_string(),
             base_api: options.api_override.clone(),
             dcs_api: options.api_for_download_config_specs.clone(),
+            long_poll_api: options.rulesets_long_poll_api.clone(),
             statsig_metadata: json!(HashMap::from([
                 ("sdkType".to_string(), "rust-server".to_string()),
                 ("sdkVersion".to_string(), VERSION.to_string())
             ])),
-        }
+        })
     }
 
     pub async fn download_config_specs(&self, since_time: u64) -> Option<String> {
         let res = match self.dcs_api == "https://api.statsigcdn.com/v1" {
-            true => self
-                .make_get_request(&format!(
-                    "download_config_specs/{}.json?sinceTime={}",
-                    self.secret, since_time
-                ))
-                .await
-                .ok()?,
+            true => {
+                self.make_get_request(
+                    NetworkEndpoint::DownloadConfigSpecs,
+                    &format!(
+                        "download_config_specs/{}.json?sinceTime={}",
+                        self.secret, since_time
+                    ),
+                )
+                .await?
+            }
             false => {
                 let mut body = HashMap::new();
                 body.insert("sinceTime", json!(since_time));
-                self.make_post_request("download_config_specs", &mut body)
-                    .await
-                    .ok()?
+                self.make_post_request(
+                    NetworkEndpoint::DownloadConfigSpecs,
+                    "download_config_specs",
+                    &mut body,
+                )
+                .await?
             }
         };
 
-        if res.status().as_u16() > 299 {
-            println!(
-                "[Statsig] Unexpected status code ({}) for download_config_specs.",
-                res.status()
+        if res.status > 299 {
+            warn!(
+                "Unexpected status code ({}) for download_config_specs.",
+                res.status
+            );
+            return None;
+        }
+
+        Some(res.body)
+    }
+
+    pub fn has_long_poll_api(&self) -> bool {
+        self.long_poll_api.is_some()
+    }
+
+    // Held open by the server until there is an update or it times out the
+    // request, in which case it may answer with an empty body.
+    pub async fn long_poll_config_specs(&self, since_time: u64) -> Option<String> {
+        let mut body = HashMap::new();
+        body.insert("sinceTime", json!(since_time));
+        let res = self
+            .make_post_request(
+                NetworkEndpoint::LongPollConfigSpecs,
+                "download_config_specs",
+                &mut body,
+            )
+            .await?;
+
+        if res.status > 299 {
+            warn!(
+                "Unexpected status code ({}) for download_config_specs long-poll.",
+                res.status
+            );
+            return None;
+        }
+
+        Some(res.body)
+    }
+
+    pub async fn get_id_lists(&self) -> Option<String> {
+        let mut body = HashMap::new();
+        let res = self
+            .make_post_request(NetworkEndpoint::GetIdLists, "get_id_lists", &mut body)
+            .await?;
+
+        if res.status > 299 {
+            warn!("Unexpected status code ({}) for get_id_lists.", res.status);
+            return None;
+        }
+
+        Some(res.body)
+    }
+
+    pub async fn download_id_list(&self, url: &str, range_start: u64) -> Option<String> {
+        let res = self
+            .provider
+            .send(NetworkRequest {
+                endpoint: NetworkEndpoint::DownloadIdList,
+                method: NetworkMethod::Get,
+                url: url.to_string(),
+                headers: HashMap::from([("Range".to_string(), format!("bytes={}-", range_start))]),
+                body: None,
+                timeout: None,
+            })
+            .await?;
+
+        if res.status > 299 {
+            warn!(
+                "Unexpected status code ({}) for id list download.",
+                res.status
             );
             return None;
         }
 
-        res.text().await.ok()
+        Some(res.body)
     }
 
-    pub async fn send_events(&self, events: Vec<StatsigEventInternal>) -> Option<Response> {
+    pub async fn send_events(&self, events: &[StatsigEventInternal]) -> Option<NetworkResponse> {
         let mut body = HashMap::from([("events", json!(events))]);
 
-        self.make_post_request("log_event", &mut body).await.ok()
+        self.make_post_request(NetworkEndpoint::LogEvent, "log_event", &mut body)
+            .await
     }
 
-    fn get_api_url(&self, endpoint: &str) -> String {
-        let api = match endpoint.starts_with("download_config_specs") {
-            true => self.dcs_api.clone(),
-            false => self.base_api.clone(),
+    fn get_api_url(&self, endpoint: NetworkEndpoint, path: &str) -> String {
+        let api = match endpoint {
+            NetworkEndpoint::DownloadConfigSpecs => &self.dcs_api,
+            NetworkEndpoint::LongPollConfigSpecs => {
+                self.long_poll_api.as_ref().unwrap_or(&self.dcs_api)
+            }
+            _ => &self.base_api,
         };
         match api.ends_with('/') {
-            true => format!("{}{}", api, endpoint),
-            false => format!("{}/{}", api, endpoint),
+            true => format!("{}{}", api, path),
+            false => format!("{}/{}", api, path),
         }
     }
 
-    async fn make_get_request(&self, endpoint: &str) -> Result<Response, Error> {
-        self.client.get(self.get_api_url(endpoint)).send().await
+    async fn make_get_request(
+        &self,
+        endpoint: NetworkEndpoint,
+        path: &str,
+    ) -> Option<NetworkResponse> {
+        self.provider
+            .send(NetworkRequest {
+                endpoint,
+                method: NetworkMethod::Get,
+                url: self.get_api_url(endpoint, path),
+                headers: HashMap::new(),
+                body: None,
+                timeout: None,
+            })
+            .await
     }
 
     async fn make_post_request(
         &self,
-        endpoint: &str,
+        endpoint: NetworkEndpoint,
+        path: &str,
         body: &mut HashMap<&str, Value>,
-    ) -> Result<Response, Error> {
-        let mut headers = HeaderMap::new();
-        headers.insert(
-            "STATSIG-API-KEY",
-            self.secret.parse().expect("statsig_api_key -> header"),
-        );
+    ) -> Option<NetworkResponse> {
+        let headers = HashMap::from([
+            ("STATSIG-API-KEY".to_string(), self.secret.clone()),
+            ("Content-Type".to_string(), "application/json".to_string()),
+        ]);
 
         body.insert("statsigMetadata", self.statsig_metadata.clone());
 
-        self.client
-            .post(self.get_api_url(endpoint))
-            .json(&body)
-            .headers(headers)
-            .send()
+        self.provider
+            .send(NetworkRequest {
+                endpoint,
+                method: NetworkMethod::Post,
+                url: self.get_api_url(endpoint, path),
+                headers,
+                body: serde_json::to_string(&body).ok(),
+                timeout: match endpoint {
+                    NetworkEndpoint::LongPollConfigSpecs => Some(LONG_POLL_TIMEOUT),
+                    _ => None,
+                },
+            })
             .await
     }
 }
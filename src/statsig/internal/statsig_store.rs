@@ -1,28 +1,51 @@
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use arc_swap::ArcSwap;
+use futures::future::join_all;
 use tokio::runtime::Handle;
+use tokio::sync::Notify;
+use tracing::{debug, error, instrument, warn};
 
 use crate::statsig::internal::data_types::APIDownloadedConfigsResponse::WithUpdates;
+use crate::statsig::internal::evaluation::id_list::IDList;
 use crate::statsig::internal::evaluation::specs::Specs;
+use crate::statsig::statsig_config_change::{ConfigChangeEvent, ConfigChangeListener, SpecChanges};
 use crate::statsig::statsig_datastore;
+use crate::statsig::statsig_error::StatsigError;
 use crate::{StatsigDatastore, StatsigOptions};
 use statsig_datastore::CONFIG_SPEC_KEY;
 
-use super::data_types::{APIDownloadedConfigsResponse, APIDownloadedConfigsWithUpdates, APISpec};
+use super::data_types::{
+    APIDownloadedConfigsResponse, APIDownloadedConfigsWithUpdates, APIIDListMetadata, APISpec,
+};
 use super::evaluation::eval_details::{EvalDetails, EvaluationReason};
 use super::statsig_network::StatsigNetwork;
 
+// Lower bound between long-polls, in case the server answers immediately
+const LONG_POLL_MIN_INTERVAL: Duration = Duration::from_secs(1);
+
 pub struct StatsigStore {
-    pub specs: Arc<RwLock<Specs>>,
-    pub eval_details: Arc<RwLock<EvalDetails>>,
+    // Replaced wholesale on every sync, so readers never wait on a writer
+    pub specs: Arc<ArcSwap<Specs>>,
+    pub id_lists: Arc<RwLock<HashMap<String, IDList>>>,
+    pub listeners: Arc<RwLock<Vec<ConfigChangeListener>>>,
 
     runtime_handle: Handle,
     network: Arc<StatsigNetwork>,
     datastore: Option<Arc<dyn StatsigDatastore>>,
+    bootstrap_values: Option<String>,
+    bootstrap_file_path: Option<PathBuf>,
     sync_interval_ms: u32,
+    id_lists_sync_interval_ms: u32,
+    local_mode: bool,
     is_shutdown: Arc<AtomicBool>,
+    // Wakes the sync task out of a pending long-poll on shutdown
+    shutdown_notify: Arc<Notify>,
 }
 
 impl StatsigStore {
@@ -34,24 +57,84 @@ impl StatsigStore {
         StatsigStore {
             runtime_handle: runtime_handle.clone(),
             network,
-            datastore: options.datastore.clone(),
-            specs: Arc::from(RwLock::from(Specs::new())),
+            datastore: match options.local_mode {
+                true => None,
+                false => options.datastore.clone(),
+            },
+            bootstrap_values: options.bootstrap_values.clone(),
+            bootstrap_file_path: options.bootstrap_file_path.clone(),
+            specs: Arc::new(ArcSwap::from_pointee(Self::initial_specs(
+                options.local_mode,
+            ))),
             sync_interval_ms: options.rulesets_sync_interval_ms,
+            id_lists_sync_interval_ms: options.id_lists_sync_interval_ms,
+            id_lists: Arc::new(RwLock::new(HashMap::new())),
+            listeners: Arc::new(RwLock::new(vec![])),
+            local_mode: options.local_mode,
             is_shutdown: Arc::new(AtomicBool::new(false)),
-            eval_details: Arc::new(RwLock::new(EvalDetails::default())),
+            shutdown_notify: Arc::new(Notify::new()),
         }
     }
 
-    pub async fn initialize(&self) {
-        if let Some(store) = &self.datastore {
-            store.initialize().await;
+    fn initial_specs(local_mode: bool) -> Specs {
+        let mut specs = Specs::new();
+        if local_mode {
+            specs.eval_details.reason = EvaluationReason::LocalMode;
+        }
+        specs
+    }
+
+    pub async fn initialize(&self, timeout_ms: Option<u32>) -> Option<StatsigError> {
+        if self.local_mode {
+            return None;
         }
-        self.initialize_config_specs().await;
+
+        // ID lists sync on their own task, so a timeout only stops the wait
+        // for them and doesn't count as a failed initialization
+        let network = self.network.clone();
+        let id_lists = self.id_lists.clone();
+        let id_lists_sync = self.runtime_handle.spawn(async move {
+            Self::sync_id_lists(&network, &id_lists).await;
+        });
+
+        let specs_sync = async {
+            if let Some(store) = &self.datastore {
+                store.initialize().await;
+            }
+            self.initialize_config_specs().await;
+        };
+
+        let timed_out = match timeout_ms {
+            Some(ms) => {
+                let deadline = tokio::time::Instant::now() + Duration::from_millis(ms as u64);
+                let timed_out = tokio::time::timeout_at(deadline, specs_sync).await.is_err();
+                let _ = tokio::time::timeout_at(deadline, id_lists_sync).await;
+                timed_out
+            }
+            None => {
+                specs_sync.await;
+                let _ = id_lists_sync.await;
+                false
+            }
+        };
+
+        // Keep syncing in the background even if initialization timed out
         self.spawn_bg_thread();
+        self.spawn_id_lists_bg_thread();
+
+        if timed_out {
+            return Some(StatsigError::InitializationTimeout);
+        }
+
+        match self.specs.load().last_sync_time {
+            0 => Some(StatsigError::ConfigSpecsUnavailable),
+            _ => None,
+        }
     }
 
     pub fn shutdown(&self) {
         self.is_shutdown.store(true, Ordering::Relaxed);
+        self.shutdown_notify.notify_one();
 
         if let Some(store) = &self.datastore {
             store.shutdown();
@@ -62,26 +145,46 @@ impl StatsigStore {
         &self,
         spec_type: &str,
         spec_name: &str,
-        func: impl Fn(Option<&APISpec>, EvalDetails) -> T,
+        func: impl FnOnce(Option<&APISpec>, EvalDetails) -> T,
     ) -> T {
-        let specs = self.specs.read().expect("Specs read lock");
+        let specs = self.specs.load();
         let specs_map = match spec_type {
             "config" => &specs.configs,
             "layer" => &specs.layers,
             _ => &specs.gates,
         };
 
-        func(specs_map.get(spec_name), self.get_eval_details())
+        func(specs_map.get(spec_name), specs.eval_details.clone())
+    }
+
+    // Runs `func` against a single snapshot of all specs
+    pub fn use_specs<T>(&self, func: impl FnOnce(&Specs, EvalDetails) -> T) -> T {
+        let specs = self.specs.load();
+        func(&specs, specs.eval_details.clone())
+    }
+
+    pub fn add_config_change_listener(&self, listener: ConfigChangeListener) {
+        if let Ok(mut listeners) = self.listeners.write() {
+            listeners.push(listener);
+        }
     }
 
     pub fn get_eval_details(&self) -> EvalDetails {
-        let eval_details = self.eval_details.read().unwrap();
-        eval_details.clone()
+        self.specs.load().eval_details.clone()
+    }
+
+    pub fn id_list_contains(&self, list_name: &str, hashed_id: &str) -> bool {
+        match self.id_lists.read().ok() {
+            Some(id_lists) => id_lists
+                .get(list_name)
+                .is_some_and(|list| list.ids.contains(hashed_id)),
+            None => false,
+        }
     }
 
     pub fn get_layer_name_for_experiment(&self, experiment_name: &String) -> Option<String> {
-        let specs = self.specs.read().ok()?;
-        return specs.experiment_to_layer.get(experiment_name).cloned();
+        let specs = self.specs.load();
+        specs.experiment_to_layer.get(experiment_name).cloned()
     }
 
     async fn initialize_config_specs(&self) {
@@ -90,33 +193,32 @@ impl StatsigStore {
             response = Self::fetch_and_process_configs_from_datstore(
                 &**store,
                 &self.specs,
-                &self.eval_details,
+                &self.listeners,
             )
             .await;
         }
+        if response.is_none() {
+            response = self.fetch_and_process_configs_from_bootstrap().await;
+        }
         if response.is_none() {
             Self::fetch_and_process_configs_from_network(
                 &self.network,
                 &self.datastore,
                 &self.specs,
-                &self.eval_details,
+                &self.listeners,
             )
             .await;
         }
-        if let Ok(mut eval_details) = self.eval_details.write() {
-            if let Ok(specs) = self.specs.read() {
-                eval_details.init_time = specs.last_sync_time
-            }
-        }
     }
 
     fn spawn_bg_thread(&self) {
         let network = self.network.clone();
         let datastore = self.datastore.clone();
-        let specs: Arc<RwLock<Specs>> = self.specs.clone();
-        let eval_details = self.eval_details.clone();
+        let specs: Arc<ArcSwap<Specs>> = self.specs.clone();
+        let listeners = self.listeners.clone();
         let interval = Duration::from_millis(self.sync_interval_ms as u64);
         let is_shutdown = self.is_shutdown.clone();
+        let shutdown_notify = self.shutdown_notify.clone();
 
         self.runtime_handle.spawn(async move {
             loop {
@@ -124,27 +226,47 @@ impl StatsigStore {
                     break;
                 }
 
+                let datastore_for_updates = datastore
+                    .as_ref()
+                    .filter(|store| store.should_be_used_for_querying_updates());
+
+                // Prefer the long-poll when configured, polling on the regular
+                // interval whenever it fails and then trying it again.
+                if network.has_long_poll_api() && datastore_for_updates.is_none() {
+                    let started = Instant::now();
+                    let result = tokio::select! {
+                        result = Self::long_poll_and_process_configs(
+                            &network,
+                            &datastore,
+                            &specs,
+                            &listeners,
+                        ) => result,
+                        _ = shutdown_notify.notified() => break,
+                    };
+
+                    if result.is_some() {
+                        if let Some(wait) = LONG_POLL_MIN_INTERVAL.checked_sub(started.elapsed()) {
+                            tokio::time::sleep(wait).await;
+                        }
+                        continue;
+                    }
+                    warn!("Config specs long-poll failed, falling back to polling");
+                }
+
                 tokio::time::sleep(interval).await;
 
                 if is_shutdown.load(Ordering::Relaxed) {
                     break;
                 }
 
-                match &datastore {
-                    Some(store) if store.should_be_used_for_querying_updates() => {
-                        Self::fetch_and_process_configs_from_datstore(
-                            &**store,
-                            &specs,
-                            &eval_details,
-                        )
-                        .await;
+                match datastore_for_updates {
+                    Some(store) => {
+                        Self::fetch_and_process_configs_from_datstore(&**store, &specs, &listeners)
+                            .await;
                     }
-                    _ => {
+                    None => {
                         Self::fetch_and_process_configs_from_network(
-                            &network,
-                            &datastore,
-                            &specs,
-                            &eval_details,
+                            &network, &datastore, &specs, &listeners,
                         )
                         .await;
                     }
@@ -153,6 +275,99 @@ impl StatsigStore {
         });
     }
 
+    fn spawn_id_lists_bg_thread(&self) {
+        let network = self.network.clone();
+        let id_lists = self.id_lists.clone();
+        let interval = Duration::from_millis(self.id_lists_sync_interval_ms as u64);
+        let is_shutdown = self.is_shutdown.clone();
+
+        self.runtime_handle.spawn(async move {
+            loop {
+                if is_shutdown.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                tokio::time::sleep(interval).await;
+
+                if is_shutdown.load(Ordering::Relaxed) {
+                    break;
+                }
+
+                Self::sync_id_lists(&network, &id_lists).await;
+            }
+        });
+    }
+
+    #[instrument(skip_all)]
+    async fn sync_id_lists(
+        network: &StatsigNetwork,
+        id_lists: &RwLock<HashMap<String, IDList>>,
+    ) -> Option<()> {
+        let response = match network.get_id_lists().await {
+            Some(data) => data,
+            None => {
+                warn!("No result returned from get_id_lists");
+                return None;
+            }
+        };
+        let server_lists =
+            serde_json::from_str::<HashMap<String, APIIDListMetadata>>(&response).ok()?;
+
+        // Work out which lists have new content, resetting any list whose
+        // file was replaced on the server.
+        let mut pending = vec![];
+        if let Ok(mut local_lists) = id_lists.write() {
+            local_lists.retain(|name, _| server_lists.contains_key(name));
+
+            for (name, server_list) in server_lists.iter() {
+                let url = match &server_list.url {
+                    Some(url) => url.clone(),
+                    None => continue,
+                };
+
+                let local_list = local_lists
+                    .entry(name.clone())
+                    .or_insert_with(|| IDList::new(server_list));
+
+                if server_list.file_id != local_list.file_id
+                    && server_list.creation_time >= local_list.creation_time
+                {
+                    *local_list = IDList::new(server_list);
+                }
+
+                if server_list.size > local_list.size {
+                    pending.push((name.clone(), url, local_list.size));
+                }
+            }
+        }
+
+        let downloads = pending
+            .into_iter()
+            .map(|(name, url, range_start)| async move {
+                let changes = network.download_id_list(&url, range_start).await;
+                (name, range_start, changes)
+            });
+        let results = join_all(downloads).await;
+
+        if let Ok(mut local_lists) = id_lists.write() {
+            for (name, range_start, changes) in results {
+                let changes = match changes {
+                    Some(changes) => changes,
+                    None => continue,
+                };
+                let local_list = match local_lists.get_mut(&name) {
+                    Some(list) if list.size == range_start => list,
+                    _ => continue,
+                };
+                if !local_list.apply_changes(&changes) {
+                    local_lists.remove(&name);
+                }
+            }
+        }
+
+        Some(())
+    }
+
     async fn save_config_specs_to_datastore(
         datastore: &Option<Arc<dyn StatsigDatastore>>,
         specs: &str,
@@ -164,12 +379,9 @@ impl StatsigStore {
 
     async fn fetch_config_specs_from_network(
         network: &StatsigNetwork,
-        specs: &RwLock<Specs>,
+        specs: &ArcSwap<Specs>,
     ) -> Option<String> {
-        let last_sync_time = match specs.read().ok() {
-            Some(t) => t.last_sync_time,
-            _ => 0,
-        };
+        let last_sync_time = specs.load().last_sync_time;
 
         network.download_config_specs(last_sync_time).await
     }
@@ -178,87 +390,141 @@ impl StatsigStore {
         datastore.get(CONFIG_SPEC_KEY).await
     }
 
+    #[instrument(name = "sync_config_specs", skip_all)]
     async fn fetch_and_process_configs_from_network(
         network: &StatsigNetwork,
         datastore: &Option<Arc<dyn StatsigDatastore>>,
-        specs: &RwLock<Specs>,
-        eval_details: &Arc<RwLock<EvalDetails>>,
+        specs: &ArcSwap<Specs>,
+        listeners: &RwLock<Vec<ConfigChangeListener>>,
     ) -> Option<()> {
         let response = Self::fetch_config_specs_from_network(network, specs).await;
         let configs = match response {
             Some(ref data) => Self::parse_config_specs(data),
             None => {
-                println!("[Statsig] No result returned from download_config_specs");
+                warn!("No result returned from download_config_specs");
                 return None;
             }
         };
         if let Some(WithUpdates(r)) = configs {
-            let valid_update = Self::set_downloaded_config_specs(
-                specs,
-                r.clone(),
-                EvaluationReason::Network,
-                eval_details,
-            );
-            match valid_update {
-                Some(()) => {
-                    let specs_json = serde_json::to_string(&r);
-                    if let Ok(specs_string) = specs_json {
-                        Self::save_config_specs_to_datastore(datastore, &specs_string).await;
-                    }
-                    return Some(());
-                }
-                None => return None,
-            }
+            return Self::process_configs_from_network(r, datastore, specs, listeners).await;
         }
         None
     }
 
+    // Returns None if the long-poll request failed, so the caller can fall
+    // back to polling. Responses without updates still count as success.
+    #[instrument(name = "long_poll_config_specs", skip_all)]
+    async fn long_poll_and_process_configs(
+        network: &StatsigNetwork,
+        datastore: &Option<Arc<dyn StatsigDatastore>>,
+        specs: &ArcSwap<Specs>,
+        listeners: &RwLock<Vec<ConfigChangeListener>>,
+    ) -> Option<()> {
+        let last_sync_time = specs.load().last_sync_time;
+
+        let response = network.long_poll_config_specs(last_sync_time).await?;
+        if response.trim().is_empty() {
+            return Some(());
+        }
+
+        if let WithUpdates(r) = Self::parse_config_specs(&response)? {
+            Self::process_configs_from_network(r, datastore, specs, listeners).await;
+        }
+        Some(())
+    }
+
+    async fn process_configs_from_network(
+        downloaded_configs: APIDownloadedConfigsWithUpdates,
+        datastore: &Option<Arc<dyn StatsigDatastore>>,
+        specs: &ArcSwap<Specs>,
+        listeners: &RwLock<Vec<ConfigChangeListener>>,
+    ) -> Option<()> {
+        let specs_json = serde_json::to_string(&downloaded_configs);
+        Self::set_downloaded_config_specs(
+            specs,
+            downloaded_configs,
+            EvaluationReason::Network,
+            listeners,
+        )?;
+
+        if let Ok(specs_string) = specs_json {
+            Self::save_config_specs_to_datastore(datastore, &specs_string).await;
+        }
+        Some(())
+    }
+
     async fn fetch_and_process_configs_from_datstore(
         datastore: &dyn StatsigDatastore,
-        specs: &RwLock<Specs>,
-        eval_details: &Arc<RwLock<EvalDetails>>,
+        specs: &ArcSwap<Specs>,
+        listeners: &RwLock<Vec<ConfigChangeListener>>,
     ) -> Option<()> {
         let response = Self::fetch_config_specs_from_datastore(datastore).await?;
         let configs = Self::parse_config_specs(&response);
         if let Some(WithUpdates(r)) = configs {
-            Self::set_downloaded_config_specs(
-                specs,
-                r,
-                EvaluationReason::DataAdapter,
-                eval_details,
-            );
+            Self::set_downloaded_config_specs(specs, r, EvaluationReason::DataAdapter, listeners);
             return Some(());
         }
         None
     }
 
+    async fn fetch_and_process_configs_from_bootstrap(&self) -> Option<()> {
+        let bootstrap = match (&self.bootstrap_values, &self.bootstrap_file_path) {
+            (Some(values), path) => {
+                if path.is_some() {
+                    warn!("bootstrap_file_path is ignored when bootstrap_values is set");
+                }
+                values.clone()
+            }
+            (None, Some(path)) => match tokio::fs::read_to_string(path).await {
+                Ok(contents) => contents,
+                Err(e) => {
+                    error!("Failed to read bootstrap file: {}", e);
+                    return None;
+                }
+            },
+            (None, None) => return None,
+        };
+
+        match Self::parse_config_specs(&bootstrap) {
+            Some(WithUpdates(r)) => Self::set_downloaded_config_specs(
+                &self.specs,
+                r,
+                EvaluationReason::Bootstrap,
+                &self.listeners,
+            ),
+            _ => {
+                error!("Failed to parse bootstrap values");
+                None
+            }
+        }
+    }
+
     fn set_downloaded_config_specs(
-        specs: &RwLock<Specs>,
+        specs: &ArcSwap<Specs>,
         downloaded_configs: APIDownloadedConfigsWithUpdates,
         eval_reason: EvaluationReason,
-        eval_details: &Arc<RwLock<EvalDetails>>,
+        listeners: &RwLock<Vec<ConfigChangeListener>>,
     ) -> Option<()> {
-        let last_sync_time = match specs.read().ok() {
-            Some(t) => t.last_sync_time,
-            _ => 0,
-        };
-        if downloaded_configs.time < last_sync_time {
+        if downloaded_configs.time < specs.load().last_sync_time {
             return None;
         }
         let mut new_specs = Specs::new();
         for feature_gate in downloaded_configs.feature_gates {
+            feature_gate.compile_conditions();
             new_specs
                 .gates
                 .insert(feature_gate.name.to_string(), feature_gate);
         }
 
         for dynamic_config in downloaded_configs.dynamic_configs {
+            dynamic_config.compile_conditions();
             new_specs
                 .configs
                 .insert(dynamic_config.name.to_string(), dynamic_config);
         }
 
         for layer_config in downloaded_configs.layer_configs {
+            layer_config.compile_conditions();
             new_specs
                 .layers
                 .insert(layer_config.name.to_string(), layer_config);
@@ -272,18 +538,273 @@ impl StatsigStore {
             }
         }
 
-        if let Ok(mut mut_specs) = specs.write() {
-            new_specs.last_sync_time = downloaded_configs.time;
-            mut_specs.update(new_specs);
+        new_specs.last_sync_time = downloaded_configs.time;
+        new_specs.eval_details.reason = eval_reason;
+        new_specs.eval_details.config_sync_time = downloaded_configs.time;
+
+        // Swap in the new snapshot unless a newer one was stored meanwhile
+        let (previous, new_specs) = loop {
+            let current = specs.load_full();
+            if downloaded_configs.time < current.last_sync_time {
+                return None;
+            }
+            // init_time stays at the sync time of the first specs loaded
+            new_specs.eval_details.init_time = match current.last_sync_time {
+                0 => downloaded_configs.time,
+                _ => current.eval_details.init_time,
+            };
+            let candidate = Arc::new(new_specs);
+            let swapped = specs.compare_and_swap(&current, candidate.clone());
+            if Arc::ptr_eq(&swapped, &current) {
+                break (current, candidate);
+            }
+            // Another sync won the race. The failed swap dropped its copy, so
+            // this takes the snapshot back without cloning it.
+            new_specs = Arc::try_unwrap(candidate).unwrap_or_else(|shared| (*shared).clone());
         };
-        if let Ok(mut mut_eval_details) = eval_details.write() {
-            mut_eval_details.config_sync_time = downloaded_configs.time;
-            mut_eval_details.reason = eval_reason
+
+        debug!(sync_time = downloaded_configs.time, "Config specs updated");
+
+        let change_event = ConfigChangeEvent {
+            sync_time: downloaded_configs.time,
+            gates: SpecChanges::diff(&previous.gates, &new_specs.gates),
+            configs: SpecChanges::diff(&previous.configs, &new_specs.configs),
+            layers: SpecChanges::diff(&previous.layers, &new_specs.layers),
+        };
+
+        if !change_event.is_empty() {
+            // Call listeners without holding the lock, so they can add other
+            // listeners, and keep a panicking one from ending the sync task
+            let listeners = match listeners.read() {
+                Ok(listeners) => listeners.clone(),
+                Err(_) => vec![],
+            };
+            for listener in listeners {
+                let result = panic::catch_unwind(AssertUnwindSafe(|| listener(&change_event)));
+                if result.is_err() {
+                    error!("Config change listener panicked");
+                }
+            }
         }
-        return Some(());
+        Some(())
     }
 
     fn parse_config_specs(text: &str) -> Option<APIDownloadedConfigsResponse> {
         serde_json::from_str::<APIDownloadedConfigsResponse>(text).ok()
     }
 }
+
+#[tokio::test]
+async fn test_initialize_from_bootstrap() {
+    use crate::statsig::internal::test_utils::{
+        make_gate_spec, make_specs, public_conditions, FakeNetworkProvider,
+    };
+    use crate::statsig::internal::StatsigDriver;
+    use crate::{NetworkRequest, StatsigUser};
+
+    let gate = make_gate_spec("a_gate", public_conditions());
+    let specs = make_specs(vec![gate], vec![], vec![], 1);
+    let file_path =
+        std::env::temp_dir().join(format!("statsig_bootstrap_{}.json", std::process::id()));
+    std::fs::write(&file_path, &specs).unwrap();
+
+    // The network never has specs, so only the bootstrap can initialize
+    let make_options = |bootstrap_values: Option<String>, bootstrap_file_path| StatsigOptions {
+        network_provider: Some(Arc::new(FakeNetworkProvider(|_: &NetworkRequest| {
+            "{}".to_string()
+        }))),
+        bootstrap_values,
+        bootstrap_file_path,
+        ..StatsigOptions::default()
+    };
+    let user = StatsigUser::with_user_id("a-user".to_string());
+
+    let driver = StatsigDriver::new("secret key", make_options(Some(specs), None)).unwrap();
+    let details = driver.initialize().await;
+    assert!(details.init_success);
+    assert_eq!(details.source, EvaluationReason::Bootstrap);
+    assert!(driver.check_gate(&user, "a_gate"));
+
+    let driver =
+        StatsigDriver::new("secret key", make_options(None, Some(file_path.clone()))).unwrap();
+    let details = driver.initialize().await;
+    assert!(details.init_success);
+    assert_eq!(details.source, EvaluationReason::Bootstrap);
+    assert!(driver.check_gate(&user, "a_gate"));
+    std::fs::remove_file(&file_path).unwrap();
+
+    let invalid = Some("not json".to_string());
+    let driver = StatsigDriver::new("secret key", make_options(invalid, None)).unwrap();
+    let details = driver.initialize().await;
+    assert!(!details.init_success);
+    assert!(matches!(
+        details.error,
+        Some(StatsigError::ConfigSpecsUnavailable)
+    ));
+    assert!(!driver.check_gate(&user, "a_gate"));
+}
+
+// Starts a mock server whose first long-poll answers with an update after
+// `first_long_poll_delay`, and whose later long-polls hang
+#[cfg(test)]
+async fn serve_long_poll_update(first_long_poll_delay: Duration) -> std::net::SocketAddr {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    use crate::statsig::internal::test_utils::{make_gate_spec, make_specs, public_conditions};
+
+    // a_gate only passes from the long-polled update on
+    fn make_update(time: u64, pass_percentage: f64) -> String {
+        let mut gate = make_gate_spec("a_gate", public_conditions());
+        gate["rules"][0]["passPercentage"] = json!(pass_percentage);
+        make_specs(vec![gate], vec![], vec![], time)
+    }
+
+    async fn read_request(stream: &mut TcpStream) -> String {
+        let mut data = vec![];
+        let mut buf = [0u8; 4096];
+        loop {
+            let n = stream.read(&mut buf).await.unwrap_or(0);
+            if n == 0 {
+                break;
+            }
+            data.extend_from_slice(&buf[..n]);
+
+            let text = String::from_utf8_lossy(&data).to_lowercase();
+            if let Some(header_end) = text.find("\r\n\r\n") {
+                let content_length = text[..header_end]
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length:"))
+                    .and_then(|len| len.trim().parse::<usize>().ok())
+                    .unwrap_or(0);
+                if data.len() >= header_end + 4 + content_length {
+                    break;
+                }
+            }
+        }
+        String::from_utf8_lossy(&data).to_string()
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let long_polls = Arc::new(AtomicUsize::new(0));
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let long_polls = long_polls.clone();
+            tokio::spawn(async move {
+                let request = read_request(&mut stream).await;
+                let body = if request.starts_with("POST /v1/download_config_specs ") {
+                    make_update(1, 0.0)
+                } else if request.starts_with("POST /stream/download_config_specs ") {
+                    match long_polls.fetch_add(1, Ordering::SeqCst) {
+                        0 => tokio::time::sleep(first_long_poll_delay).await,
+                        _ => tokio::time::sleep(Duration::from_secs(60)).await,
+                    }
+                    make_update(2, 100.0)
+                } else {
+                    "{}".to_string()
+                };
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+    addr
+}
+
+#[cfg(test)]
+fn long_poll_options(addr: std::net::SocketAddr) -> StatsigOptions {
+    StatsigOptions {
+        api_override: format!("http://{}/v1", addr),
+        api_for_download_config_specs: format!("http://{}/v1", addr),
+        rulesets_long_poll_api: Some(format!("http://{}/stream", addr)),
+        rulesets_sync_interval_ms: 60_000,
+        ..StatsigOptions::default()
+    }
+}
+
+#[tokio::test]
+async fn test_long_poll_config_updates() {
+    use crate::statsig::internal::test_utils::wait_until;
+    use crate::statsig::internal::StatsigDriver;
+    use crate::StatsigUser;
+
+    let addr = serve_long_poll_update(Duration::ZERO).await;
+    let driver = StatsigDriver::new("secret key", long_poll_options(addr)).unwrap();
+    let details = driver.initialize().await;
+    assert!(details.init_success);
+
+    // The update arrives well before the polling interval would elapse
+    let user = StatsigUser::with_user_id("a-user".to_string());
+    assert!(wait_until(|| driver.check_gate(&user, "a_gate")).await);
+}
+
+#[tokio::test]
+async fn test_long_poll_outlives_network_timeout() {
+    use crate::statsig::internal::test_utils::wait_until;
+    use crate::statsig::internal::StatsigDriver;
+    use crate::StatsigUser;
+
+    let addr = serve_long_poll_update(Duration::from_millis(1_500)).await;
+    let options = StatsigOptions {
+        network_timeout_ms: Some(500),
+        ..long_poll_options(addr)
+    };
+    let driver = StatsigDriver::new("secret key", options).unwrap();
+    let details = driver.initialize().await;
+    assert!(details.init_success);
+
+    let user = StatsigUser::with_user_id("a-user".to_string());
+    assert!(wait_until(|| driver.check_gate(&user, "a_gate")).await);
+}
+
+#[test]
+fn test_concurrent_syncs_keep_the_newest_specs() {
+    use std::thread;
+
+    use crate::statsig::internal::test_utils::{make_gate_spec, make_specs, public_conditions};
+
+    let download = |time: u64| -> APIDownloadedConfigsWithUpdates {
+        let gates = vec![make_gate_spec("a_gate", public_conditions())];
+        serde_json::from_str(&make_specs(gates, vec![], vec![], time)).unwrap()
+    };
+    let listeners = RwLock::new(vec![]);
+    let specs = ArcSwap::from_pointee(Specs::new());
+    StatsigStore::set_downloaded_config_specs(
+        &specs,
+        download(1),
+        EvaluationReason::Network,
+        &listeners,
+    );
+
+    // One thread syncs even times and the other odd ones, so their swaps
+    // interleave and older specs often arrive after newer ones
+    thread::scope(|scope| {
+        for first in [2, 3] {
+            let updates: Vec<_> = (first..=1_001).step_by(2).map(download).collect();
+            let (specs, listeners) = (&specs, &listeners);
+            scope.spawn(move || {
+                for update in updates {
+                    StatsigStore::set_downloaded_config_specs(
+                        specs,
+                        update,
+                        EvaluationReason::Network,
+                        listeners,
+                    );
+                }
+            });
+        }
+    });
+
+    let specs = specs.load();
+    assert_eq!(specs.last_sync_time, 1_001);
+    assert_eq!(specs.eval_details.config_sync_time, 1_001);
+    assert_eq!(specs.eval_details.init_time, 1);
+}
@@ -0,0 +1,126 @@
+// Fixtures shared by the unit tests
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tokio::task::spawn_blocking;
+
+use crate::statsig::internal::StatsigDriver;
+
+use crate::{NetworkEndpoint, NetworkProvider, NetworkRequest, NetworkResponse, StatsigOptions};
+
+// Answers every request with the body returned by `handler`
+pub struct FakeNetworkProvider<F>(pub F);
+
+#[async_trait]
+impl<F> NetworkProvider for FakeNetworkProvider<F>
+where
+    F: Fn(&NetworkRequest) -> String + Send + Sync,
+{
+    async fn send(&self, request: NetworkRequest) -> Option<NetworkResponse> {
+        Some(NetworkResponse {
+            status: 200,
+            body: (self.0)(&request),
+        })
+    }
+}
+
+pub fn public_conditions() -> Value {
+    json!([{"type": "public", "idType": "userID"}])
+}
+
+// A gate with a single rule that passes when `conditions` do
+pub fn make_gate_spec(name: &str, conditions: Value) -> Value {
+    json!({
+        "name": name,
+        "type": "feature_gate",
+        "salt": "a_salt",
+        "defaultValue": false,
+        "enabled": true,
+        "idType": "userID",
+        "entity": "feature_gate",
+        "rules": [{
+            "name": "rule",
+            "passPercentage": 100.0,
+            "returnValue": true,
+            "id": format!("{}_rule", name),
+            "idType": "userID",
+            "conditions": conditions,
+        }],
+    })
+}
+
+// A config, experiment or layer that returns `value` to everyone
+pub fn make_config_spec(name: &str, entity: &str, value: Value) -> Value {
+    let mut spec = make_gate_spec(name, public_conditions());
+    spec["type"] = json!("dynamic_config");
+    spec["entity"] = json!(entity);
+    spec["defaultValue"] = json!({});
+    spec["rules"][0]["returnValue"] = value;
+    spec
+}
+
+// A download_config_specs response
+pub fn make_specs(gates: Vec<Value>, configs: Vec<Value>, layers: Vec<Value>, time: u64) -> String {
+    json!({
+        "feature_gates": gates,
+        "dynamic_configs": configs,
+        "layer_configs": layers,
+        "has_updates": true,
+        "time": time,
+    })
+    .to_string()
+}
+
+// Events sent to log_event, in the order they were sent
+pub type LoggedEvents = Arc<Mutex<Vec<Value>>>;
+
+// Options whose network serves `specs` and records every logged event
+pub fn serve_specs(specs: String) -> (StatsigOptions, LoggedEvents) {
+    let logged = LoggedEvents::default();
+    let server_logged = logged.clone();
+    let handler = move |request: &NetworkRequest| match request.endpoint {
+        NetworkEndpoint::DownloadConfigSpecs => specs.clone(),
+        NetworkEndpoint::LogEvent => {
+            let body = request.body.as_deref().unwrap_or_default();
+            let body: Value = serde_json::from_str(body).unwrap_or_default();
+            if let Some(events) = body["events"].as_array() {
+                server_logged.lock().unwrap().extend(events.iter().cloned());
+            }
+            "{}".to_string()
+        }
+        _ => "{}".to_string(),
+    };
+    let options = StatsigOptions {
+        network_provider: Some(Arc::new(FakeNetworkProvider(handler))),
+        ..StatsigOptions::default()
+    };
+    (options, logged)
+}
+
+// The eventName of every logged event
+pub fn event_names(logged: &LoggedEvents) -> Vec<String> {
+    let logged = logged.lock().unwrap();
+    logged
+        .iter()
+        .filter_map(|event| event["eventName"].as_str().map(str::to_string))
+        .collect()
+}
+
+// Shuts the driver down, which flushes its queued events. Shutdown blocks,
+// so it runs off the test's runtime thread.
+pub async fn shutdown(driver: Arc<StatsigDriver>) {
+    spawn_blocking(move || driver.shutdown()).await.unwrap();
+}
+
+// Polls `condition` for up to 5 seconds
+pub async fn wait_until(condition: impl Fn() -> bool) -> bool {
+    for _ in 0..50 {
+        if condition() {
+            return true;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    false
+}
@@ -1,6 +1,16 @@
 pub mod internal;
+pub mod statsig_all_evaluations;
+pub mod statsig_client;
+pub mod statsig_config_change;
 pub mod statsig_datastore;
 pub mod statsig_error;
+pub mod statsig_evaluation_trace;
 pub mod statsig_event;
+pub mod statsig_geo_ip_resolver;
+pub mod statsig_initialize_details;
+pub mod statsig_network_provider;
 pub mod statsig_options;
 pub mod statsig_user;
+pub mod statsig_user_agent_resolver;
+pub mod statsig_user_context;
+pub mod statsig_user_persistent_storage;
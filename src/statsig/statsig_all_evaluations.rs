@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+
+use serde_json::Value;
+
+use crate::{DynamicConfig, FeatureGate, Layer};
+
+// Every gate, dynamic config, experiment and layer evaluated for one user,
+// keyed by name. Returned by evaluate_all.
+pub struct AllEvaluations {
+    pub gates: HashMap<String, FeatureGate>,
+    pub configs: HashMap<String, DynamicConfig<Value>>,
+    pub experiments: HashMap<String, DynamicConfig<Value>>,
+    pub layers: HashMap<String, Layer>,
+}
+
+#[tokio::test]
+async fn test_evaluate_all() {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use crate::statsig::internal::test_utils::{
+        make_config_spec, make_gate_spec, make_specs, public_conditions, serve_specs,
+    };
+    use crate::statsig::internal::StatsigDriver;
+    use crate::{EvaluationReason, StatsigUser};
+
+    let mut segment = make_gate_spec("a_segment", public_conditions());
+    segment["entity"] = json!("segment");
+    let gates = vec![make_gate_spec("a_gate", public_conditions()), segment];
+    let configs = vec![
+        make_config_spec("a_config", "dynamic_config", json!({"a": 1})),
+        make_config_spec("an_experiment", "experiment", json!({"b": 2})),
+    ];
+    let layers = vec![make_config_spec("a_layer", "layer", json!({"c": 3}))];
+    let (options, _) = serve_specs(make_specs(gates, configs, layers, 1));
+    let driver = Arc::new(StatsigDriver::new("secret key", options).unwrap());
+    driver.initialize().await;
+    driver.override_gate("a_gate", false, Some("a-user"));
+
+    let user = StatsigUser::with_user_id("a-user".to_string());
+    let all = driver.evaluate_all_with_exposure_logging_disabled(&user);
+
+    assert_eq!(all.gates.len(), 1);
+    assert!(!all.gates["a_gate"].value);
+    assert_eq!(
+        all.gates["a_gate"].evaluation_details.reason,
+        EvaluationReason::LocalOverride
+    );
+
+    assert_eq!(all.configs.len(), 1);
+    assert_eq!(all.configs["a_config"].value, Some(json!({"a": 1})));
+    assert_eq!(all.experiments.len(), 1);
+    let experiment = &all.experiments["an_experiment"];
+    assert_eq!(experiment.value, Some(json!({"b": 2})));
+    assert_eq!(all.layers.len(), 1);
+    assert_eq!(all.layers["a_layer"].get("c", 0), 3);
+}
@@ -0,0 +1,317 @@
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tokio::task::spawn_blocking;
+
+use crate::statsig::internal::StatsigDriver;
+use crate::statsig::statsig_config_change::ConfigChangeEvent;
+use crate::statsig::statsig_error::StatsigError;
+use crate::statsig::statsig_initialize_details::InitializeDetails;
+use crate::statsig::statsig_user_context::StatsigUserContext;
+use crate::{
+    AllEvaluations, DynamicConfig, EvaluationTrace, FeatureGate, Layer, StatsigEvent,
+    StatsigOptions, StatsigUser,
+};
+
+// An independent Statsig instance. Use this instead of the global `Statsig`
+// singleton when a process needs to talk to more than one Statsig project.
+// Clones share the same underlying instance.
+#[derive(Clone)]
+pub struct StatsigClient {
+    driver: Arc<StatsigDriver>,
+}
+
+impl StatsigClient {
+    pub fn new(secret: &str, options: StatsigOptions) -> Result<Self, StatsigError> {
+        let driver =
+            StatsigDriver::new(secret, options).map_err(|_| StatsigError::InstantiationFailure)?;
+
+        Ok(StatsigClient {
+            driver: Arc::new(driver),
+        })
+    }
+
+    pub async fn initialize(&self) -> InitializeDetails {
+        self.driver.initialize().await
+    }
+
+    pub async fn shutdown(&self) -> Option<StatsigError> {
+        let driver = self.driver.clone();
+        match spawn_blocking(move || driver.shutdown()).await {
+            Ok(_t) => None,
+            Err(_e) => Some(StatsigError::ShutdownFailure),
+        }
+    }
+
+    pub fn check_gate(&self, user: &StatsigUser, gate_name: &str) -> bool {
+        self.driver.check_gate(user, gate_name)
+    }
+
+    pub fn get_feature_gate(&self, user: &StatsigUser, gate_name: &str) -> FeatureGate {
+        self.driver.get_feature_gate(user, gate_name)
+    }
+
+    pub fn get_config<T: DeserializeOwned>(
+        &self,
+        user: &StatsigUser,
+        config_name: &str,
+    ) -> DynamicConfig<T> {
+        self.driver.get_config(user, config_name)
+    }
+
+    pub fn get_experiment<T: DeserializeOwned>(
+        &self,
+        user: &StatsigUser,
+        experiment_name: &str,
+    ) -> DynamicConfig<T> {
+        self.get_config(user, experiment_name)
+    }
+
+    pub fn get_experiment_with_persisted_assignment<T: DeserializeOwned>(
+        &self,
+        user: &StatsigUser,
+        experiment_name: &str,
+    ) -> DynamicConfig<T> {
+        self.driver
+            .get_experiment_with_persisted_assignment(user, experiment_name)
+    }
+
+    pub fn get_layer(&self, user: &StatsigUser, layer_name: &str) -> Layer {
+        self.driver.get_layer(user, layer_name)
+    }
+
+    pub fn check_gate_with_exposure_logging_disabled(
+        &self,
+        user: &StatsigUser,
+        gate_name: &str,
+    ) -> bool {
+        self.driver
+            .check_gate_with_exposure_logging_disabled(user, gate_name)
+    }
+
+    pub fn get_feature_gate_with_exposure_logging_disabled(
+        &self,
+        user: &StatsigUser,
+        gate_name: &str,
+    ) -> FeatureGate {
+        self.driver
+            .get_feature_gate_with_exposure_logging_disabled(user, gate_name)
+    }
+
+    pub fn get_config_with_exposure_logging_disabled<T: DeserializeOwned>(
+        &self,
+        user: &StatsigUser,
+        config_name: &str,
+    ) -> DynamicConfig<T> {
+        self.driver
+            .get_config_with_exposure_logging_disabled(user, config_name)
+    }
+
+    pub fn get_experiment_with_exposure_logging_disabled<T: DeserializeOwned>(
+        &self,
+        user: &StatsigUser,
+        experiment_name: &str,
+    ) -> DynamicConfig<T> {
+        self.get_config_with_exposure_logging_disabled(user, experiment_name)
+    }
+
+    // Parameters read from the returned layer do not log exposures.
+    pub fn get_layer_with_exposure_logging_disabled(
+        &self,
+        user: &StatsigUser,
+        layer_name: &str,
+    ) -> Layer {
+        self.driver
+            .get_layer_with_exposure_logging_disabled(user, layer_name)
+    }
+
+    pub fn manually_log_gate_exposure(&self, user: &StatsigUser, gate_name: &str) {
+        self.driver.manually_log_gate_exposure(user, gate_name)
+    }
+
+    pub fn manually_log_config_exposure(&self, user: &StatsigUser, config_name: &str) {
+        self.driver.manually_log_config_exposure(user, config_name)
+    }
+
+    pub fn manually_log_experiment_exposure(&self, user: &StatsigUser, experiment_name: &str) {
+        self.manually_log_config_exposure(user, experiment_name)
+    }
+
+    pub fn manually_log_layer_parameter_exposure(
+        &self,
+        user: &StatsigUser,
+        layer_name: &str,
+        parameter_name: &str,
+    ) {
+        self.driver
+            .manually_log_layer_parameter_exposure(user, layer_name, parameter_name)
+    }
+
+    pub fn log_event(&self, user: &StatsigUser, event: StatsigEvent) {
+        self.driver.log_event(user, event)
+    }
+
+    // Registers a callback that is run whenever a config specs sync adds,
+    // removes or changes a gate, config or layer.
+    pub fn add_config_change_listener(
+        &self,
+        listener: impl Fn(&ConfigChangeEvent) + Send + Sync + 'static,
+    ) {
+        self.driver.add_config_change_listener(Arc::new(listener))
+    }
+
+    // Number of events that were dropped after failing to send
+    pub fn get_dropped_event_count(&self) -> u64 {
+        self.driver.get_dropped_event_count()
+    }
+
+    pub fn get_client_initialize_response(&self, user: &StatsigUser) -> Value {
+        self.driver.get_client_initialize_response(user)
+    }
+
+    // Creates a context that caches gate and config results for the user,
+    // for handlers that check many related gates.
+    pub fn with_user_context(&self, user: &StatsigUser) -> StatsigUserContext {
+        StatsigUserContext::new(self.driver.clone(), user)
+    }
+
+    // Evaluates every gate, config, experiment and layer for the user in one
+    // pass, e.g. to attach to a request context.
+    pub fn evaluate_all(&self, user: &StatsigUser) -> AllEvaluations {
+        self.driver.evaluate_all(user)
+    }
+
+    pub fn evaluate_all_with_exposure_logging_disabled(
+        &self,
+        user: &StatsigUser,
+    ) -> AllEvaluations {
+        self.driver
+            .evaluate_all_with_exposure_logging_disabled(user)
+    }
+
+    // Walks through how the gate is evaluated for the user, rule by rule,
+    // for debugging targeting. No exposures are logged.
+    pub fn explain_gate(&self, user: &StatsigUser, gate_name: &str) -> EvaluationTrace {
+        self.driver.explain_gate(user, gate_name)
+    }
+
+    pub fn explain_config(&self, user: &StatsigUser, config_name: &str) -> EvaluationTrace {
+        self.driver.explain_config(user, config_name)
+    }
+
+    // Forces a value for the gate, for every user or only for the given user
+    // ID / custom ID. Overrides take precedence over the downloaded rules.
+    pub fn override_gate(&self, gate_name: &str, value: bool, id: Option<&str>) {
+        self.driver.override_gate(gate_name, value, id)
+    }
+
+    pub fn override_config(&self, config_name: &str, value: Value, id: Option<&str>) {
+        self.driver.override_config(config_name, value, id)
+    }
+
+    pub fn override_layer(&self, layer_name: &str, value: Value, id: Option<&str>) {
+        self.driver.override_layer(layer_name, value, id)
+    }
+
+    pub fn remove_gate_override(&self, gate_name: &str, id: Option<&str>) {
+        self.driver.remove_gate_override(gate_name, id)
+    }
+
+    pub fn remove_config_override(&self, config_name: &str, id: Option<&str>) {
+        self.driver.remove_config_override(config_name, id)
+    }
+
+    pub fn remove_layer_override(&self, layer_name: &str, id: Option<&str>) {
+        self.driver.remove_layer_override(layer_name, id)
+    }
+
+    pub fn remove_all_overrides(&self) {
+        self.driver.remove_all_overrides()
+    }
+
+    #[doc(hidden)]
+    #[cfg(statsig_kong)]
+    pub fn __unsafe_shutdown(&self) {
+        self.driver.__unsafe_shutdown();
+    }
+}
+
+#[tokio::test]
+async fn test_invalid_network_options_fail_instantiation() {
+    use std::collections::HashMap;
+
+    let options = StatsigOptions {
+        network_proxy_url: Some("http://bad proxy".to_string()),
+        ..StatsigOptions::default()
+    };
+    let result = StatsigClient::new("secret key", options);
+    assert!(matches!(result, Err(StatsigError::InstantiationFailure)));
+
+    let options = StatsigOptions {
+        network_default_headers: Some(HashMap::from([(
+            "bad header".to_string(),
+            "value".to_string(),
+        )])),
+        ..StatsigOptions::default()
+    };
+    let result = StatsigClient::new("secret key", options);
+    assert!(matches!(result, Err(StatsigError::InstantiationFailure)));
+
+    let options = StatsigOptions {
+        network_proxy_url: Some("http://localhost:3128".to_string()),
+        ..StatsigOptions::default()
+    };
+    assert!(StatsigClient::new("secret key", options).is_ok());
+}
+
+#[tokio::test]
+async fn test_clients_are_independent() {
+    use serde_json::json;
+
+    use crate::statsig::internal::test_utils::{
+        event_names, make_config_spec, make_gate_spec, make_specs, public_conditions, serve_specs,
+    };
+
+    // Each project passes a_gate differently and gives a_layer its own value
+    let make_project = |pass_percentage: f64, layer_value: &str| {
+        let mut gate = make_gate_spec("a_gate", public_conditions());
+        gate["rules"][0]["passPercentage"] = json!(pass_percentage);
+        let layer = make_config_spec("a_layer", "layer", json!({"p": layer_value}));
+        serve_specs(make_specs(vec![gate], vec![], vec![layer], 1))
+    };
+    let (options_a, logged_a) = make_project(100.0, "a");
+    let (options_b, logged_b) = make_project(0.0, "b");
+    let client_a = StatsigClient::new("secret key", options_a).unwrap();
+    let client_b = StatsigClient::new("secret key", options_b).unwrap();
+    client_a.initialize().await;
+    client_b.initialize().await;
+    let user = StatsigUser::with_user_id("a-user".to_string());
+
+    assert!(client_a.check_gate(&user, "a_gate"));
+    assert!(!client_b.check_gate(&user, "a_gate"));
+    let layer_a = client_a.get_layer(&user, "a_layer");
+    let layer_b = client_b.get_layer(&user, "a_layer");
+    assert_eq!(layer_a.get("p", String::new()), "a");
+
+    // Shutting down and dropping one client leaves the other working
+    client_a.shutdown().await;
+    drop(layer_a);
+    drop(client_a);
+    assert!(!client_b.check_gate(&user, "a_gate"));
+    assert_eq!(layer_b.get("p", String::new()), "b");
+    client_b.shutdown().await;
+
+    // Layer parameter exposures go to the client that created the layer
+    let expected = vec!["statsig::gate_exposure", "statsig::layer_exposure"];
+    assert_eq!(event_names(&logged_a), expected);
+    assert_eq!(event_names(&logged_b), expected);
+    assert_eq!(
+        logged_a.lock().unwrap()[1]["metadata"]["parameterName"],
+        "p"
+    );
+    assert_eq!(
+        logged_b.lock().unwrap()[1]["metadata"]["parameterName"],
+        "p"
+    );
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::statsig::internal::APISpec;
+
+// Called on the background sync task whenever new config specs change any
+// gate, config or layer. Listeners should return quickly. A panic in a
+// listener is caught and logged.
+pub type ConfigChangeListener = Arc<dyn Fn(&ConfigChangeEvent) + Send + Sync>;
+
+#[derive(Clone, Debug)]
+pub struct ConfigChangeEvent {
+    pub sync_time: u64,
+    pub gates: SpecChanges,
+    pub configs: SpecChanges,
+    pub layers: SpecChanges,
+}
+
+impl ConfigChangeEvent {
+    pub fn is_empty(&self) -> bool {
+        self.gates.is_empty() && self.configs.is_empty() && self.layers.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SpecChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    // Specs present before and after the sync whose version differs
+    pub changed: Vec<String>,
+}
+
+impl SpecChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    pub(crate) fn diff(old: &HashMap<String, APISpec>, new: &HashMap<String, APISpec>) -> Self {
+        let mut changes = SpecChanges::default();
+
+        for (name, spec) in new.iter() {
+            match old.get(name) {
+                None => changes.added.push(name.clone()),
+                Some(old_spec) if old_spec.version != spec.version => {
+                    changes.changed.push(name.clone())
+                }
+                _ => {}
+            }
+        }
+
+        for name in old.keys() {
+            if !new.contains_key(name) {
+                changes.removed.push(name.clone());
+            }
+        }
+
+        changes.added.sort();
+        changes.removed.sort();
+        changes.changed.sort();
+        changes
+    }
+}
+
+#[test]
+fn test_spec_changes_diff() {
+    use serde_json::json;
+
+    let make_specs = |versions: &[(&str, i64)]| -> HashMap<String, APISpec> {
+        versions
+            .iter()
+            .map(|(name, version)| {
+                let spec = serde_json::from_value(json!({
+                    "name": name,
+                    "type": "feature_gate",
+                    "salt": "a_salt",
+                    "defaultValue": false,
+                    "enabled": true,
+                    "idType": "userID",
+                    "entity": "feature_gate",
+                    "version": version,
+                    "rules": [],
+                }))
+                .unwrap();
+                (name.to_string(), spec)
+            })
+            .collect()
+    };
+
+    let old = make_specs(&[("kept", 1), ("changed", 1), ("removed", 1)]);
+    let new = make_specs(&[("kept", 1), ("changed", 2), ("added_b", 1), ("added_a", 1)]);
+    let changes = SpecChanges::diff(&old, &new);
+    assert_eq!(changes.added, vec!["added_a", "added_b"]);
+    assert_eq!(changes.removed, vec!["removed"]);
+    assert_eq!(changes.changed, vec!["changed"]);
+
+    assert!(SpecChanges::diff(&new, &new).is_empty());
+}
@@ -12,4 +12,8 @@ pub enum StatsigError {
     Uninitialized,
     #[error("Was unable to gracefully shutdown the Statsig instance")]
     ShutdownFailure,
+    #[error("Initialization did not complete before init_timeout_ms")]
+    InitializationTimeout,
+    #[error("Failed to load config specs from any source during initialization")]
+    ConfigSpecsUnavailable,
 }
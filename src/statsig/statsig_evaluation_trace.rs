@@ -0,0 +1,170 @@
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::statsig::internal::{APICondition, APIRule, EvalResult};
+use crate::EvalDetails;
+
+// A step by step record of how a gate or config was evaluated for a user,
+// returned by explain_gate and explain_config.
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvaluationTrace {
+    pub name: String,
+    // "gate", "config" or "layer"
+    pub spec_type: String,
+    pub found: bool,
+    pub enabled: bool,
+    // Rules in the order they were checked. Evaluation stops at the first
+    // rule whose conditions pass.
+    pub rules: Vec<RuleTrace>,
+    pub rule_id: String,
+    pub value: Value,
+    pub evaluation_details: EvalDetails,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleTrace {
+    pub rule_id: String,
+    pub name: String,
+    pub conditions: Vec<ConditionTrace>,
+    pub conditions_passed: bool,
+    pub pass_percentage: f64,
+    // The user's hash bucket for this rule, from 0 to 9999. The rule passes
+    // if it is below pass_percentage * 100.
+    pub bucket: Option<u64>,
+    pub passed: bool,
+    // Set when the rule hands evaluation off to another config
+    pub delegate: Option<Box<EvaluationTrace>>,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConditionTrace {
+    pub condition_type: String,
+    pub field: Option<String>,
+    pub operator: Option<String>,
+    pub target_value: Value,
+    // The value read from the user, or the nested gate's value
+    pub user_value: Value,
+    pub passed: bool,
+    pub unsupported: bool,
+    // Set for pass_gate and fail_gate conditions
+    pub nested_gate: Option<Box<EvaluationTrace>>,
+}
+
+impl EvaluationTrace {
+    pub(crate) fn new(name: &str, spec_type: &str) -> Self {
+        EvaluationTrace {
+            name: name.to_string(),
+            spec_type: spec_type.to_string(),
+            found: false,
+            enabled: false,
+            rules: vec![],
+            rule_id: "".to_string(),
+            value: Value::Null,
+            evaluation_details: EvalDetails::default(),
+        }
+    }
+
+    pub(crate) fn set_result(&mut self, result: &EvalResult) {
+        self.rule_id = result.rule_id.clone();
+        self.value = match self.spec_type.as_str() {
+            "gate" => json!(result.bool_value),
+            _ => result.json_value.clone().unwrap_or(Value::Null),
+        };
+        self.evaluation_details = result.evaluation_details.clone();
+    }
+}
+
+impl RuleTrace {
+    pub(crate) fn new(rule: &APIRule) -> Self {
+        RuleTrace {
+            rule_id: rule.id.clone(),
+            name: rule.name.clone(),
+            conditions: vec![],
+            conditions_passed: false,
+            pass_percentage: rule.pass_percentage,
+            bucket: None,
+            passed: false,
+            delegate: None,
+        }
+    }
+}
+
+impl ConditionTrace {
+    pub(crate) fn new(condition: &APICondition) -> Self {
+        ConditionTrace {
+            condition_type: condition.condition_type.clone(),
+            field: condition.field.clone(),
+            operator: condition.operator.clone(),
+            target_value: condition.target_value.clone().unwrap_or(Value::Null),
+            user_value: Value::Null,
+            passed: false,
+            unsupported: false,
+            nested_gate: None,
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_explain_gate() {
+    use crate::statsig::internal::test_utils::{
+        make_gate_spec, make_specs, public_conditions, serve_specs,
+    };
+    use crate::statsig::internal::StatsigDriver;
+    use crate::StatsigUser;
+
+    let mut outer_gate = make_gate_spec(
+        "outer_gate",
+        json!([{
+            "type": "user_field",
+            "operator": "any",
+            "field": "email",
+            "targetValue": ["b@example.com"],
+            "idType": "userID",
+        }]),
+    );
+    let mut nested_rule = make_gate_spec(
+        "outer_gate",
+        json!([{"type": "pass_gate", "targetValue": "a_gate", "idType": "userID"}]),
+    )["rules"][0]
+        .clone();
+    nested_rule["id"] = json!("nested_rule");
+    outer_gate["rules"]
+        .as_array_mut()
+        .unwrap()
+        .push(nested_rule);
+
+    let gates = vec![outer_gate, make_gate_spec("a_gate", public_conditions())];
+    let (options, _) = serve_specs(make_specs(gates, vec![], vec![], 1));
+    let driver = StatsigDriver::new("secret key", options).unwrap();
+    driver.initialize().await;
+
+    let mut user = StatsigUser::with_user_id("a-user".to_string());
+    user.email = Some("a@example.com".to_string());
+
+    let trace = driver.explain_gate(&user, "outer_gate");
+    assert!(trace.found);
+    assert!(trace.enabled);
+    assert_eq!(trace.value, json!(true));
+    assert_eq!(trace.rule_id, "nested_rule");
+    assert_eq!(trace.rules.len(), 2);
+
+    let email_rule = &trace.rules[0];
+    assert!(!email_rule.passed);
+    assert!(!email_rule.conditions_passed);
+    assert_eq!(email_rule.conditions[0].condition_type, "user_field");
+    assert_eq!(email_rule.conditions[0].user_value, json!("a@example.com"));
+
+    let nested_rule = &trace.rules[1];
+    assert!(nested_rule.passed);
+    let nested_gate = nested_rule.conditions[0].nested_gate.as_ref().unwrap();
+    assert_eq!(nested_gate.name, "a_gate");
+    assert_eq!(nested_gate.value, json!(true));
+    assert_eq!(nested_gate.rules[0].conditions[0].condition_type, "public");
+
+    let missing = driver.explain_gate(&user, "missing_gate");
+    assert!(!missing.found);
+    assert!(missing.rules.is_empty());
+}
@@ -0,0 +1,70 @@
+use serde_json::Value;
+
+use crate::StatsigUser;
+
+// Supplies location data for ip_based conditions, e.g. from a MaxMind
+// database or a country header set by a CDN. Called during evaluation, so
+// lookups must not block on I/O.
+pub trait GeoIpResolver: Send + Sync {
+    // Returns the value of `field` ("country", "region", "city", ...) for the
+    // user, usually derived from `user.ip`. Returning None falls back to the
+    // built-in IP table, which only answers "country", so other fields then
+    // evaluate as missing.
+    fn resolve(&self, user: &StatsigUser, field: &str) -> Option<Value>;
+}
+
+#[tokio::test]
+async fn test_geo_ip_resolver_fields() {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use crate::statsig::internal::test_utils::{make_gate_spec, make_specs, serve_specs};
+    use crate::statsig::internal::StatsigDriver;
+    use crate::StatsigOptions;
+
+    struct HeaderGeoIpResolver {}
+
+    impl GeoIpResolver for HeaderGeoIpResolver {
+        fn resolve(&self, user: &StatsigUser, field: &str) -> Option<Value> {
+            if user.ip.as_deref() != Some("203.0.113.7") {
+                return None;
+            }
+            match field {
+                "region" => Some(json!("CA")),
+                "city" => Some(json!("San Francisco")),
+                _ => None,
+            }
+        }
+    }
+
+    let make_condition = |field: &str, target: &str| {
+        json!([{
+            "type": "ip_based",
+            "operator": "any",
+            "field": field,
+            "targetValue": [target],
+            "idType": "userID",
+        }])
+    };
+    let gates = vec![
+        make_gate_spec("region_gate", make_condition("region", "ca")),
+        make_gate_spec("city_gate", make_condition("city", "San Francisco")),
+    ];
+    let (options, _) = serve_specs(make_specs(gates, vec![], vec![], 1));
+    let options = StatsigOptions {
+        geo_ip_resolver: Some(Arc::new(HeaderGeoIpResolver {})),
+        ..options
+    };
+    let driver = StatsigDriver::new("secret key", options).unwrap();
+    driver.initialize().await;
+
+    let mut user = StatsigUser::with_user_id("a-user".to_string());
+    user.ip = Some("203.0.113.7".to_string());
+    assert!(driver.check_gate(&user, "region_gate"));
+    assert!(driver.check_gate(&user, "city_gate"));
+
+    user.ip = Some("198.51.100.1".to_string());
+    assert!(!driver.check_gate(&user, "region_gate"));
+    assert!(!driver.check_gate(&user, "city_gate"));
+}
@@ -0,0 +1,84 @@
+use crate::statsig::statsig_error::StatsigError;
+use crate::EvaluationReason;
+
+#[derive(Debug)]
+pub struct InitializeDetails {
+    pub duration_ms: u64,
+    // True once config specs were loaded from some source within the timeout
+    pub init_success: bool,
+    // Where the specs came from: Network, DataAdapter or Bootstrap.
+    // LocalMode if local_mode is set, Uninitialized if nothing was loaded.
+    pub source: EvaluationReason,
+    pub config_sync_time: u64,
+    pub error: Option<StatsigError>,
+}
+
+impl InitializeDetails {
+    pub(crate) fn from_error(error: StatsigError) -> Self {
+        InitializeDetails {
+            duration_ms: 0,
+            init_success: false,
+            source: EvaluationReason::Uninitialized,
+            config_sync_time: 0,
+            error: Some(error),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_initialize_details() {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+
+    use crate::statsig::internal::test_utils::{make_gate_spec, make_specs, public_conditions};
+    use crate::statsig::internal::StatsigDriver;
+    use crate::{
+        NetworkEndpoint, NetworkProvider, NetworkRequest, NetworkResponse, StatsigOptions,
+    };
+
+    // Serves specs right away but takes a while to list ID lists
+    struct SlowIdListsProvider {}
+
+    #[async_trait]
+    impl NetworkProvider for SlowIdListsProvider {
+        async fn send(&self, request: NetworkRequest) -> Option<NetworkResponse> {
+            let body = match request.endpoint {
+                NetworkEndpoint::DownloadConfigSpecs => {
+                    let gate = make_gate_spec("a_gate", public_conditions());
+                    make_specs(vec![gate], vec![], vec![], 1)
+                }
+                NetworkEndpoint::GetIdLists => {
+                    tokio::time::sleep(Duration::from_secs(2)).await;
+                    "{}".to_string()
+                }
+                _ => "{}".to_string(),
+            };
+            Some(NetworkResponse { status: 200, body })
+        }
+    }
+
+    let options = StatsigOptions {
+        network_provider: Some(Arc::new(SlowIdListsProvider {})),
+        init_timeout_ms: Some(500),
+        ..StatsigOptions::default()
+    };
+    let driver = StatsigDriver::new("secret key", options).unwrap();
+    let details = driver.initialize().await;
+    assert!(details.init_success);
+    assert!(details.error.is_none());
+    assert_eq!(details.source, EvaluationReason::Network);
+    assert_eq!(details.config_sync_time, 1);
+    assert!(details.duration_ms < 2_000);
+
+    let options = StatsigOptions {
+        local_mode: true,
+        ..StatsigOptions::default()
+    };
+    let driver = StatsigDriver::new("secret key", options).unwrap();
+    let details = driver.initialize().await;
+    assert!(details.init_success);
+    assert_eq!(details.source, EvaluationReason::LocalMode);
+    assert_eq!(details.config_sync_time, 0);
+}
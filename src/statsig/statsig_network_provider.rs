@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Client, ClientBuilder, Proxy};
+
+use crate::StatsigOptions;
+
+// Without a TLS backend reqwest can't reach the HTTPS Statsig API, and every
+// request would fail at runtime instead
+#[cfg(not(any(feature = "native-tls", feature = "rustls")))]
+compile_error!("statsig needs a TLS backend: enable the `native-tls` or `rustls` feature");
+
+// New endpoints may be added, so matches on this need a wildcard arm
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum NetworkEndpoint {
+    DownloadConfigSpecs,
+    LongPollConfigSpecs,
+    LogEvent,
+    GetIdLists,
+    DownloadIdList,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NetworkMethod {
+    Get,
+    Post,
+}
+
+pub struct NetworkRequest {
+    // Which Statsig call this request is for, so providers can route or fake it
+    pub endpoint: NetworkEndpoint,
+    pub method: NetworkMethod,
+    pub url: String,
+    pub headers: HashMap<String, String>,
+    pub body: Option<String>,
+    // Overrides the provider's default timeout, e.g. for long-polls that the
+    // server holds open
+    pub timeout: Option<Duration>,
+}
+
+pub struct NetworkResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+#[async_trait] // when implementing this trait, use the #[async_trait] macro
+pub trait NetworkProvider: Send + Sync {
+    // Returns None if the request could not be completed, e.g. on a
+    // connection error. Non-2xx responses should still be returned.
+    async fn send(&self, request: NetworkRequest) -> Option<NetworkResponse>;
+}
+
+// The default provider, backed by reqwest.
+pub struct ReqwestNetworkProvider {
+    client: Client,
+}
+
+impl ReqwestNetworkProvider {
+    pub fn new() -> Self {
+        Self::with_client(Client::new())
+    }
+
+    pub fn with_client(client: Client) -> Self {
+        ReqwestNetworkProvider { client }
+    }
+
+    // Builds the client from the network_* fields on StatsigOptions. Fails if
+    // any of them are invalid, rather than silently skipping e.g. a proxy.
+    pub fn from_options(options: &StatsigOptions) -> Result<Self, String> {
+        Self::build_client(options).map(Self::with_client)
+    }
+
+    fn build_client(options: &StatsigOptions) -> Result<Client, String> {
+        let mut builder = ClientBuilder::new();
+
+        if let Some(timeout_ms) = options.network_timeout_ms {
+            builder = builder.timeout(Duration::from_millis(timeout_ms as u64));
+        }
+
+        if let Some(connect_timeout_ms) = options.network_connect_timeout_ms {
+            builder = builder.connect_timeout(Duration::from_millis(connect_timeout_ms as u64));
+        }
+
+        if let Some(proxy_url) = &options.network_proxy_url {
+            builder = builder.proxy(Proxy::https(proxy_url).map_err(|e| e.to_string())?);
+        }
+
+        if let Some(headers) = &options.network_default_headers {
+            let mut header_map = HeaderMap::new();
+            for (key, value) in headers.iter() {
+                header_map.insert(
+                    HeaderName::try_from(key.as_str()).map_err(|e| e.to_string())?,
+                    HeaderValue::try_from(value.as_str()).map_err(|e| e.to_string())?,
+                );
+            }
+            builder = builder.default_headers(header_map);
+        }
+
+        if let Some(user_agent) = &options.network_user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+
+        builder.build().map_err(|e| e.to_string())
+    }
+}
+
+impl Default for ReqwestNetworkProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl NetworkProvider for ReqwestNetworkProvider {
+    async fn send(&self, request: NetworkRequest) -> Option<NetworkResponse> {
+        let mut builder = match request.method {
+            NetworkMethod::Get => self.client.get(&request.url),
+            NetworkMethod::Post => self.client.post(&request.url),
+        };
+
+        for (key, value) in request.headers.iter() {
+            builder = builder.header(key, value);
+        }
+
+        if let Some(body) = request.body {
+            builder = builder.body(body);
+        }
+
+        if let Some(timeout) = request.timeout {
+            builder = builder.timeout(timeout);
+        }
+
+        let res = builder.send().await.ok()?;
+        let status = res.status().as_u16();
+        let body = res.text().await.ok()?;
+
+        Some(NetworkResponse { status, body })
+    }
+}
+
+#[tokio::test]
+async fn test_initialize_with_network_provider() {
+    use std::sync::Arc;
+
+    use crate::statsig::internal::test_utils::{
+        make_gate_spec, make_specs, public_conditions, FakeNetworkProvider,
+    };
+    use crate::statsig::internal::StatsigDriver;
+    use crate::{EvaluationReason, StatsigUser};
+
+    let specs = make_specs(
+        vec![make_gate_spec("a_gate", public_conditions())],
+        vec![],
+        vec![],
+        1,
+    );
+    let handler = move |request: &NetworkRequest| match request.endpoint {
+        NetworkEndpoint::DownloadConfigSpecs => specs.clone(),
+        _ => "{}".to_string(),
+    };
+    let options = StatsigOptions {
+        network_provider: Some(Arc::new(FakeNetworkProvider(handler))),
+        ..StatsigOptions::default()
+    };
+    let driver = StatsigDriver::new("secret key", options).unwrap();
+    driver.initialize().await;
+
+    let user = StatsigUser::with_user_id("a-user".to_string());
+    let gate = driver.get_feature_gate(&user, "a_gate");
+    assert!(gate.value);
+    assert_eq!(gate.rule_id, "a_gate_rule");
+    assert_eq!(gate.evaluation_details.reason, EvaluationReason::Network);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_default_options_time_out_hung_requests() {
+    use tokio::net::TcpListener;
+
+    // Accepts connections but never answers
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut streams = vec![];
+        while let Ok((stream, _)) = listener.accept().await {
+            streams.push(stream);
+        }
+    });
+
+    let provider = ReqwestNetworkProvider::from_options(&StatsigOptions::default()).unwrap();
+    let started = tokio::time::Instant::now();
+    let response = provider
+        .send(NetworkRequest {
+            endpoint: NetworkEndpoint::DownloadConfigSpecs,
+            method: NetworkMethod::Post,
+            url: format!("http://{}/v1/download_config_specs", addr),
+            headers: HashMap::new(),
+            body: None,
+            timeout: None,
+        })
+        .await;
+
+    assert!(response.is_none());
+    assert_eq!(started.elapsed().as_secs(), 10);
+}
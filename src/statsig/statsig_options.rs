@@ -1,17 +1,64 @@
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::Arc;
 
-use crate::StatsigDatastore;
+use crate::{
+    GeoIpResolver, NetworkProvider, StatsigDatastore, UserAgentResolver, UserPersistentStorage,
+};
 
 pub struct StatsigOptions {
     pub environment: Option<HashMap<String, String>>,
     pub api_override: String,
     pub api_for_download_config_specs: String,
     pub rulesets_sync_interval_ms: u32,
+    // Base URL of a long-poll endpoint for config spec updates. When set, a
+    // download_config_specs request is kept open there so changes apply as
+    // soon as the server answers. Polling every rulesets_sync_interval_ms
+    // takes over while the endpoint is unreachable. These requests have their
+    // own timeout of several minutes instead of network_timeout_ms.
+    pub rulesets_long_poll_api: Option<String>,
+    // Maximum time to wait for specs during initialize. Syncing continues in
+    // the background if it is exceeded. ID lists are waited for within the
+    // same budget, but do not cause a timeout.
+    pub init_timeout_ms: Option<u32>,
+    pub id_lists_sync_interval_ms: u32,
     pub logger_max_queue_size: u32,
     pub logger_flush_interval_ms: u32,
+    // Skips loading the bundled user agent parser. A user_agent_resolver is
+    // still used if given. The bundled parser and IP table can also be
+    // compiled out with the ua-parser and geo-ip features.
     pub disable_user_agent_support: bool,
+    // Source of fields for ua_based conditions, consulted before the bundled
+    // parser.
+    pub user_agent_resolver: Option<Arc<dyn UserAgentResolver>>,
+    // A uap-core regexes.yaml to parse user agents with instead of the
+    // bundled one. Requires the ua-parser feature.
+    pub user_agent_regex_path: Option<PathBuf>,
+    // Source of location fields for ip_based conditions. The built-in table
+    // only knows countries.
+    pub geo_ip_resolver: Option<Arc<dyn GeoIpResolver>>,
     pub datastore: Option<Arc<dyn StatsigDatastore>>,
+    // Where get_experiment_with_persisted_assignment keeps each unit's
+    // experiment group
+    pub user_persistent_storage: Option<Arc<dyn UserPersistentStorage>>,
+    // Transport used for all requests to Statsig. Defaults to reqwest.
+    pub network_provider: Option<Arc<dyn NetworkProvider>>,
+    // Settings for the default reqwest transport. Ignored when a
+    // network_provider is given. Invalid values make instantiation fail.
+    // Requests time out after 10 seconds by default; None waits forever.
+    pub network_timeout_ms: Option<u32>,
+    pub network_connect_timeout_ms: Option<u32>,
+    pub network_proxy_url: Option<String>,
+    pub network_default_headers: Option<HashMap<String, String>>,
+    pub network_user_agent: Option<String>,
+    // A download_config_specs response to initialize from before going to
+    // the network, given either inline or as a path to a JSON file. If both
+    // are set, bootstrap_values is used and the file is not read.
+    pub bootstrap_values: Option<String>,
+    pub bootstrap_file_path: Option<PathBuf>,
+    // Never touch the network. Events are dropped and every evaluation
+    // returns its default value, unless a local override is set.
+    pub local_mode: bool,
 }
 
 impl Default for StatsigOptions {
@@ -21,10 +68,26 @@ impl Default for StatsigOptions {
             api_override: "https://statsigapi.net/v1".to_string(),
             api_for_download_config_specs: "https://api.statsigcdn.com/v1".to_string(),
             rulesets_sync_interval_ms: 10_000,
+            rulesets_long_poll_api: None,
+            init_timeout_ms: None,
+            id_lists_sync_interval_ms: 60_000,
             logger_max_queue_size: 500,
             logger_flush_interval_ms: 60_000,
             disable_user_agent_support: false,
+            user_agent_resolver: None,
+            user_agent_regex_path: None,
+            geo_ip_resolver: None,
             datastore: None,
+            user_persistent_storage: None,
+            network_provider: None,
+            network_timeout_ms: Some(10_000),
+            network_connect_timeout_ms: None,
+            network_proxy_url: None,
+            network_default_headers: None,
+            network_user_agent: None,
+            bootstrap_values: None,
+            bootstrap_file_path: None,
+            local_mode: false,
         }
     }
 }
@@ -0,0 +1,12 @@
+use serde_json::Value;
+
+use crate::StatsigUser;
+
+// Supplies user agent fields for ua_based conditions in place of the bundled
+// parser. Called during evaluation, so parsing should be fast.
+pub trait UserAgentResolver: Send + Sync {
+    // Returns the value of `field` ("os_name", "browser_version",
+    // "device_family", ...) for the user, usually parsed from
+    // `user.user_agent`. Returning None falls back to the bundled parser.
+    fn resolve(&self, user: &StatsigUser, field: &str) -> Option<Value>;
+}
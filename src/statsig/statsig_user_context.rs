@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use serde::de::DeserializeOwned;
+
+use crate::statsig::internal::{EvalCache, StatsigDriver};
+use crate::{DynamicConfig, FeatureGate, StatsigUser};
+
+// Evaluates gates and configs for a single user, remembering each result,
+// including gates checked as prerequisites of other gates, for as long as the
+// context lives. Meant to be short lived, e.g. one per request. Results are
+// recomputed after the specs are re-synced, but local overrides changed after
+// a result is cached are not picked up.
+pub struct StatsigUserContext {
+    driver: Arc<StatsigDriver>,
+    user: StatsigUser,
+    cache: EvalCache,
+}
+
+impl StatsigUserContext {
+    pub(crate) fn new(driver: Arc<StatsigDriver>, user: &StatsigUser) -> Self {
+        StatsigUserContext {
+            user: driver.get_normalized_user_copy(user),
+            driver,
+            cache: EvalCache::new(),
+        }
+    }
+
+    pub fn check_gate(&self, gate_name: &str) -> bool {
+        self.get_feature_gate(gate_name).value
+    }
+
+    pub fn get_feature_gate(&self, gate_name: &str) -> FeatureGate {
+        self.driver
+            .get_feature_gate_with_cache(&self.user, gate_name, &self.cache)
+    }
+
+    pub fn get_config<T: DeserializeOwned>(&self, config_name: &str) -> DynamicConfig<T> {
+        self.driver
+            .get_config_with_cache(&self.user, config_name, &self.cache)
+    }
+
+    pub fn get_experiment<T: DeserializeOwned>(&self, experiment_name: &str) -> DynamicConfig<T> {
+        self.get_config(experiment_name)
+    }
+}
+
+#[tokio::test]
+async fn test_user_context_cache_invalidated_on_sync() {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use serde_json::{json, Value};
+
+    use crate::statsig::internal::test_utils::{
+        make_config_spec, make_gate_spec, make_specs, public_conditions, wait_until,
+        FakeNetworkProvider,
+    };
+    use crate::{NetworkEndpoint, NetworkRequest, StatsigOptions};
+
+    let pass_gate = json!([{"type": "pass_gate", "targetValue": "a_gate", "idType": "userID"}]);
+    // Version 2 turns off a_gate and changes a_config
+    let make_version = move |time: u64| {
+        let mut a_gate = make_gate_spec("a_gate", public_conditions());
+        if time > 1 {
+            a_gate["rules"][0]["passPercentage"] = json!(0.0);
+        }
+        let outer_gate = make_gate_spec("outer_gate", pass_gate.clone());
+        let a_config = make_config_spec("a_config", "dynamic_config", json!({"time": time}));
+        make_specs(vec![a_gate, outer_gate], vec![a_config], vec![], time)
+    };
+
+    let version = Arc::new(AtomicU64::new(1));
+    let server_version = version.clone();
+    let handler = move |request: &NetworkRequest| match request.endpoint {
+        NetworkEndpoint::DownloadConfigSpecs => make_version(server_version.load(Ordering::SeqCst)),
+        _ => "{}".to_string(),
+    };
+    let options = StatsigOptions {
+        network_provider: Some(Arc::new(FakeNetworkProvider(handler))),
+        rulesets_sync_interval_ms: 50,
+        ..StatsigOptions::default()
+    };
+    let driver = Arc::new(StatsigDriver::new("secret key", options).unwrap());
+    driver.initialize().await;
+
+    let user = StatsigUser::with_user_id("a-user".to_string());
+    let context = StatsigUserContext::new(driver.clone(), &user);
+    assert!(context.check_gate("outer_gate"));
+    assert_eq!(
+        context.get_config::<Value>("a_config").value,
+        Some(json!({"time": 1}))
+    );
+
+    version.store(2, Ordering::SeqCst);
+    let synced = wait_until(|| {
+        let config = driver.get_config_with_exposure_logging_disabled::<Value>(&user, "a_config");
+        config.value == Some(json!({"time": 2}))
+    })
+    .await;
+    assert!(synced);
+
+    // Both the nested gate and the config are re-evaluated
+    assert!(!context.check_gate("outer_gate"));
+    assert_eq!(
+        context.get_config::<Value>("a_config").value,
+        Some(json!({"time": 2}))
+    );
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::statsig::internal::EvalResult;
+
+// A unit's saved experiment assignments, keyed by experiment name
+pub type UserPersistedValues = HashMap<String, StickyValues>;
+
+// Stores the group a unit was assigned to, so get_experiment_with_persisted_assignment
+// keeps returning it while the experiment is active. Keys have the form
+// "{unit_id}:{id_type}". Called during evaluation, so implementations should
+// be fast, e.g. backed by an in-process cache.
+pub trait UserPersistentStorage: Send + Sync {
+    fn load(&self, key: &str) -> Option<UserPersistedValues>;
+    fn save(&self, key: &str, experiment_name: &str, data: StickyValues);
+    fn delete(&self, key: &str, experiment_name: &str);
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StickyValues {
+    pub value: bool,
+    pub json_value: Option<Value>,
+    pub rule_id: String,
+    pub group_name: Option<String>,
+    pub secondary_exposures: Vec<HashMap<String, String>>,
+    pub undelegated_secondary_exposures: Vec<HashMap<String, String>>,
+    pub config_delegate: Option<String>,
+    pub explicit_parameters: Option<Vec<String>>,
+    pub config_version: Option<i64>,
+    // When the assignment was saved, in milliseconds since the epoch
+    pub time: u64,
+}
+
+impl StickyValues {
+    pub(crate) fn new(result: &EvalResult) -> Self {
+        StickyValues {
+            value: result.bool_value,
+            json_value: result.json_value.clone(),
+            rule_id: result.rule_id.clone(),
+            group_name: result.group_name.clone(),
+            secondary_exposures: result.secondary_exposures.clone().unwrap_or_default(),
+            undelegated_secondary_exposures: result
+                .undelegated_secondary_exposures
+                .clone()
+                .unwrap_or_default(),
+            config_delegate: result.config_delegate.clone(),
+            explicit_parameters: result.explicit_parameters.clone(),
+            config_version: result.config_version,
+            time: Utc::now().timestamp_millis() as u64,
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_experiment_with_persisted_assignment() {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::{from_value, json};
+
+    use crate::statsig::internal::test_utils::{make_config_spec, make_specs, serve_specs};
+    use crate::statsig::internal::StatsigDriver;
+    use crate::{EvaluationReason, StatsigOptions, StatsigUser};
+
+    fn make_experiment(name: &str, is_active: bool) -> Value {
+        let mut spec = make_config_spec(name, "experiment", json!({"group": "test"}));
+        spec["isActive"] = json!(is_active);
+        spec["rules"][0]["id"] = json!("test_rule");
+        spec["rules"][0]["groupName"] = json!("Test");
+        spec["rules"][0]["isExperimentGroup"] = json!(true);
+        spec
+    }
+
+    struct MemoryStorage {
+        values: Mutex<HashMap<String, UserPersistedValues>>,
+    }
+
+    impl UserPersistentStorage for MemoryStorage {
+        fn load(&self, key: &str) -> Option<UserPersistedValues> {
+            self.values.lock().unwrap().get(key).cloned()
+        }
+
+        fn save(&self, key: &str, experiment_name: &str, data: StickyValues) {
+            let mut values = self.values.lock().unwrap();
+            let entry = values.entry(key.to_string()).or_default();
+            entry.insert(experiment_name.to_string(), data);
+        }
+
+        fn delete(&self, key: &str, experiment_name: &str) {
+            if let Some(values) = self.values.lock().unwrap().get_mut(key) {
+                values.remove(experiment_name);
+            }
+        }
+    }
+
+    // The user was put in control before targeting changed
+    let control: StickyValues = from_value(json!({
+        "value": true,
+        "jsonValue": {"group": "control"},
+        "ruleId": "control_rule",
+        "groupName": "Control",
+        "secondaryExposures": [],
+        "undelegatedSecondaryExposures": [],
+        "time": 0,
+    }))
+    .unwrap();
+    let persisted = HashMap::from([
+        ("active_exp".to_string(), control.clone()),
+        ("ended_exp".to_string(), control),
+    ]);
+    let storage = Arc::new(MemoryStorage {
+        values: Mutex::new(HashMap::from([("a-user:userID".to_string(), persisted)])),
+    });
+
+    let experiments = vec![
+        make_experiment("active_exp", true),
+        make_experiment("ended_exp", false),
+        make_experiment("new_exp", true),
+    ];
+    let (options, _) = serve_specs(make_specs(vec![], experiments, vec![], 1));
+    let options = StatsigOptions {
+        user_persistent_storage: Some(storage.clone()),
+        ..options
+    };
+    let driver = StatsigDriver::new("secret key", options).unwrap();
+    driver.initialize().await;
+    let user = StatsigUser::with_user_id("a-user".to_string());
+
+    let active = driver.get_experiment_with_persisted_assignment::<Value>(&user, "active_exp");
+    assert_eq!(active.rule_id, "control_rule");
+    assert_eq!(active.value, Some(json!({"group": "control"})));
+    assert_eq!(active.evaluation_details.reason, EvaluationReason::Persisted);
+
+    let ended = driver.get_experiment_with_persisted_assignment::<Value>(&user, "ended_exp");
+    assert_eq!(ended.rule_id, "test_rule");
+    assert_eq!(ended.evaluation_details.reason, EvaluationReason::Network);
+
+    let new = driver.get_experiment_with_persisted_assignment::<Value>(&user, "new_exp");
+    assert_eq!(new.rule_id, "test_rule");
+
+    let saved = storage.load("a-user:userID").unwrap();
+    assert!(!saved.contains_key("ended_exp"));
+    assert_eq!(saved["new_exp"].group_name, Some("Test".to_string()));
+}